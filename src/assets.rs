@@ -0,0 +1,270 @@
+//! Images and other assets used by documents, with their sizes.
+
+use crate::diagnostic::Diagnostic;
+use crate::links;
+use crate::resolve::Resolution;
+use crate::visit::{walk, Control};
+use crate::wrappers::MyPosition;
+use markdown::mdast::{AttributeContent, AttributeValue, Node};
+use markdown::unist::Position;
+
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// An image, or the `src` of a JSX element.
+#[derive(Debug, Clone)]
+pub struct Asset {
+  pub url: String,
+  /// `Image`, `ImageReference`, or `Name.src` for JSX elements.
+  pub from: String,
+  pub position: Option<Position>,
+  /// The file on disk, for local assets.
+  pub path: Option<PathBuf>,
+  /// Whether `path` exists; `None` for assets that are not checked.
+  pub exists: Option<bool>,
+  /// Intrinsic size in pixels, for PNG, JPEG, GIF and SVG files.
+  pub width: Option<u32>,
+  pub height: Option<u32>,
+}
+
+impl Serialize for Asset {
+  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    let mut state = serializer.serialize_struct("Asset", 7)?;
+    state.serialize_field("url", &self.url)?;
+    state.serialize_field("from", &self.from)?;
+    if let Some(p) = self.position.clone() {
+      let pos: MyPosition = MyPosition::from(p);
+      state.serialize_field("position", &pos)?;
+    }
+    state.serialize_field("path", &self.path)?;
+    state.serialize_field("exists", &self.exists)?;
+    state.serialize_field("width", &self.width)?;
+    state.serialize_field("height", &self.height)?;
+    state.end()
+  }
+}
+
+/// The assets of `tree`, in document order; `references` are what the
+/// references in `tree` point to, as made by `resolve::resolve`. Local paths
+/// are resolved against `base`, the directory of the document, or against
+/// `root` when they start with `/`; missing files are reported in
+/// `diagnostics`.
+pub fn assets(
+  tree: &Node,
+  references: &[Option<Resolution>],
+  base: &Path,
+  root: Option<&Path>,
+  diagnostics: &mut Vec<Diagnostic>,
+) -> Vec<Asset> {
+  let mut references = references.iter();
+  let mut found: Vec<(String, String, Option<Position>)> = vec![];
+  walk(tree, |node, _| {
+    let (name, attributes, position) = match node {
+      Node::Image(n) => {
+        found.push((n.url.clone(), "Image".to_owned(), n.position.clone()));
+        return Control::Continue;
+      }
+      Node::ImageReference(n) => {
+        if let Some(Some(Resolution::Definition { url, .. })) = references.next() {
+          found.push((url.clone(), "ImageReference".to_owned(), n.position.clone()));
+        }
+        return Control::Continue;
+      }
+      Node::LinkReference(_) | Node::FootnoteReference(_) => {
+        references.next();
+        return Control::Continue;
+      }
+      Node::MdxJsxFlowElement(n) => (&n.name, &n.attributes, &n.position),
+      Node::MdxJsxTextElement(n) => (&n.name, &n.attributes, &n.position),
+      _ => return Control::Continue,
+    };
+    for attribute in attributes {
+      if let AttributeContent::Property(p) = attribute {
+        if let ("src", Some(AttributeValue::Literal(url))) = (p.name.as_str(), &p.value) {
+          let from = format!("{}.src", name.as_deref().unwrap_or("Fragment"));
+          found.push((url.clone(), from, position.clone()));
+        }
+      }
+    }
+    Control::Continue
+  });
+
+  found
+    .into_iter()
+    .map(|(url, from, position)| {
+      let path = if url.starts_with("data:") {
+        None
+      } else {
+        links::local_path(&url, base, root)
+      };
+      let exists = path.as_ref().map(|p| p.is_file());
+      if exists == Some(false) {
+        diagnostics.push(Diagnostic::error("assets", format!("`{}` not found", url), position.clone()));
+      }
+      let size = match (&path, exists) {
+        (Some(path), Some(true)) => fs::read(path).ok().and_then(|bytes| dimensions(&bytes)),
+        _ => None,
+      };
+      Asset {
+        url,
+        from,
+        position,
+        path,
+        exists,
+        width: size.map(|s| s.0),
+        height: size.map(|s| s.1),
+      }
+    })
+    .collect()
+}
+
+/// Width and height of a PNG, JPEG, GIF or SVG image.
+pub fn dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+  let be16 = |at: usize| bytes.get(at..at + 2).map(|b| u32::from(u16::from_be_bytes([b[0], b[1]])));
+  let le16 = |at: usize| bytes.get(at..at + 2).map(|b| u32::from(u16::from_le_bytes([b[0], b[1]])));
+  let be32 = |at: usize| bytes.get(at..at + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+
+  if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+    return Some((be32(16)?, be32(20)?));
+  }
+  if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+    return Some((le16(6)?, le16(8)?));
+  }
+  if bytes.starts_with(&[0xFF, 0xD8]) {
+    // walk the segments up to the start of frame, which holds the size
+    let mut at = 2;
+    while at + 4 <= bytes.len() {
+      if bytes[at] != 0xFF {
+        return None;
+      }
+      let marker = bytes[at + 1];
+      match marker {
+        0xFF => at += 1,
+        0x01 | 0xD0..=0xD7 => at += 2,
+        0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => return Some((be16(at + 7)?, be16(at + 5)?)),
+        _ => at += 2 + be16(at + 2)? as usize,
+      }
+    }
+    return None;
+  }
+  svg_dimensions(std::str::from_utf8(bytes).ok()?)
+}
+
+/// Size of an SVG from the `width` and `height` of its root element, or
+/// else from its `viewBox`.
+fn svg_dimensions(text: &str) -> Option<(u32, u32)> {
+  let start = text.find("<svg")?;
+  let tag = &text[start..start + text[start..].find('>')?];
+
+  fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut from = 0;
+    while let Some(found) = tag[from..].find(name) {
+      let at = from + found;
+      from = at + name.len();
+      if !tag[..at].ends_with(char::is_whitespace) {
+        continue;
+      }
+      let value = match tag[from..].trim_start().strip_prefix('=') {
+        Some(value) => value.trim_start(),
+        None => continue,
+      };
+      let quote = value.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+      return value[1..].split(quote).next();
+    }
+    None
+  }
+  let pixels = |value: &str| {
+    value
+      .trim()
+      .trim_end_matches("px")
+      .parse::<f64>()
+      .ok()
+      .filter(|v| *v > 0.0)
+      .map(|v| v.round() as u32)
+  };
+
+  if let (Some(width), Some(height)) = (
+    attribute(tag, "width").and_then(pixels),
+    attribute(tag, "height").and_then(pixels),
+  ) {
+    return Some((width, height));
+  }
+  let view_box = attribute(tag, "viewBox")?
+    .split(|c: char| c.is_whitespace() || c == ',')
+    .filter(|v| !v.is_empty())
+    .collect::<Vec<&str>>();
+  match view_box.as_slice() {
+    [_, _, width, height] => Some((pixels(width)?, pixels(height)?)),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::TempDir;
+  use crate::{parse_file, Options};
+
+  #[test]
+  fn reads_dimensions() {
+    let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+    png.extend(640u32.to_be_bytes());
+    png.extend(480u32.to_be_bytes());
+    assert_eq!(dimensions(&png), Some((640, 480)));
+
+    assert_eq!(dimensions(b"GIF89a\x20\x00\x10\x00"), Some((32, 16)));
+
+    let jpeg = [
+      0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, 0xFF, 0xC0, 0x00, 0x11, 0x08, 0x00, 0x78, 0x00, 0xA0,
+    ];
+    assert_eq!(dimensions(&jpeg), Some((160, 120)));
+
+    let svg = br#"<?xml version="1.0"?><svg xmlns="http://www.w3.org/2000/svg" stroke-width="2" width="24px" height="12.4">"#;
+    assert_eq!(dimensions(svg), Some((24, 12)));
+    assert_eq!(dimensions(br#"<svg viewBox="0 0 100 50"></svg>"#), Some((100, 50)));
+  }
+
+  #[test]
+  fn collects_and_checks_assets() {
+    let dir = TempDir::new("assets");
+    dir.write("a.gif", b"GIF89a\x02\x00\x03\x00");
+    dir.write("b c.gif", b"GIF89a\x04\x00\x03\x00");
+    let index = dir.write(
+      "index.mdx",
+      "![a](a.gif) ![b][b] ![c](https://example.com/c.png) ![d](b%20c.gif)\n\n<Figure src=\"missing.png\" />\n\n[b]: /a.gif\n",
+    );
+
+    let options = Options {
+      parse: markdown::ParseOptions {
+        constructs: markdown::Constructs::mdx(),
+        ..markdown::ParseOptions::default()
+      },
+      assets: true,
+      root: Some(dir.path().to_owned()),
+      ..Options::default()
+    };
+    let document = parse_file(&index, &options).unwrap();
+
+    let summary = document
+      .assets
+      .iter()
+      .flatten()
+      .map(|a| (a.from.as_str(), a.exists, a.width))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      summary,
+      vec![
+        ("Image", Some(true), Some(2)),
+        ("ImageReference", Some(true), Some(2)),
+        ("Image", None, None),
+        ("Image", Some(true), Some(4)),
+        ("Figure.src", Some(false), None),
+      ]
+    );
+    assert_eq!(document.diagnostics.len(), 1);
+  }
+}
@@ -0,0 +1,253 @@
+//! Split documents into chunks for embeddings and retrieval.
+//!
+//! Chunks follow heading boundaries and stay under a size budget where
+//! possible. Blocks are never cut: a chunk ends between two blocks, and a
+//! `Code`, `Table` or JSX element larger than the budget is a chunk of its
+//! own. Lists and block quotes that are too large are split between their
+//! items.
+
+use crate::section;
+use crate::slug;
+use crate::wrappers::MyPosition;
+use crate::Document;
+use markdown::mdast::Node;
+use markdown::unist::Position;
+
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+use std::str::FromStr;
+
+/// What the chunk budget counts.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Unit {
+  Chars,
+  /// Approximate tokens, one per four characters.
+  Tokens,
+}
+
+impl FromStr for Unit {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Unit, String> {
+    match value {
+      "chars" => Ok(Unit::Chars),
+      "tokens" => Ok(Unit::Tokens),
+      _ => Err(format!("unknown unit `{}`, expected `chars` or `tokens`", value)),
+    }
+  }
+}
+
+impl Unit {
+  pub fn measure(&self, text: &str) -> usize {
+    let chars = text.chars().count();
+    match self {
+      Unit::Chars => chars,
+      Unit::Tokens => chars.div_ceil(4),
+    }
+  }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ChunkOptions {
+  /// Largest size of a chunk, in `unit`s.
+  pub budget: usize,
+  pub unit: Unit,
+  /// Frontmatter field holding the title of the document.
+  pub title_field: String,
+}
+
+impl Default for ChunkOptions {
+  fn default() -> ChunkOptions {
+    ChunkOptions {
+      budget: 2000,
+      unit: Unit::Chars,
+      title_field: "page_title".to_owned(),
+    }
+  }
+}
+
+/// A contiguous part of a document.
+#[derive(Debug, Clone)]
+pub struct Chunk {
+  /// Index of the chunk in its document.
+  pub index: usize,
+  /// Texts of the headings the chunk is under, from the outermost.
+  pub breadcrumbs: Vec<String>,
+  /// Title from the frontmatter.
+  pub title: Option<String>,
+  /// The markdown source of the chunk.
+  pub text: String,
+  /// Size of `text`, in the unit of the budget.
+  pub size: usize,
+  pub position: Position,
+}
+
+impl Serialize for Chunk {
+  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    let mut state = serializer.serialize_struct("Chunk", 6)?;
+    state.serialize_field("index", &self.index)?;
+    state.serialize_field("breadcrumbs", &self.breadcrumbs)?;
+    state.serialize_field("title", &self.title)?;
+    state.serialize_field("text", &self.text)?;
+    state.serialize_field("size", &self.size)?;
+    state.serialize_field("position", &MyPosition(self.position.clone()))?;
+    state.end()
+  }
+}
+
+/// A block that is not split further, with the headings it is under.
+struct Block<'a> {
+  position: &'a Position,
+  breadcrumbs: Vec<String>,
+  heading: bool,
+}
+
+pub fn chunks(document: &Document, options: &ChunkOptions) -> Vec<Chunk> {
+  let size = |start: &Position, end: &Position| {
+    let span = Position {
+      start: start.start.clone(),
+      end: end.end.clone(),
+    };
+    options.unit.measure(document.source.slice(&span))
+  };
+
+  // headings open on the way, as (depth, text)
+  let mut trail: Vec<(u8, String)> = vec![];
+  let mut blocks = vec![];
+  for child in blocks_of(&document.tree) {
+    if let Node::Heading(heading) = child {
+      while matches!(trail.last(), Some((depth, _)) if *depth >= heading.depth) {
+        trail.pop();
+      }
+      trail.push((heading.depth, slug::text(child)));
+    }
+    let breadcrumbs = trail.iter().map(|(_, text)| text.clone()).collect::<Vec<String>>();
+    add_blocks(child, &breadcrumbs, &size, options.budget, &mut blocks);
+  }
+
+  let title = document
+    .frontmatter
+    .as_ref()
+    .and_then(|f| f.data.get(&options.title_field))
+    .and_then(|t| t.as_str())
+    .map(|t| t.to_owned());
+
+  let mut groups: Vec<Vec<Block>> = vec![];
+  for block in blocks {
+    let start_new = match groups.last() {
+      None => true,
+      Some(group) => {
+        // a heading starts a new chunk, unless the chunk has nothing but headings yet
+        (block.heading && group.iter().any(|b| !b.heading)) || size(group[0].position, block.position) > options.budget
+      }
+    };
+    if start_new {
+      groups.push(vec![block]);
+    } else {
+      groups.last_mut().unwrap().push(block);
+    }
+  }
+
+  groups
+    .into_iter()
+    .enumerate()
+    .map(|(index, group)| {
+      let last = group.last().unwrap();
+      let position = Position {
+        start: group[0].position.start.clone(),
+        end: last.position.end.clone(),
+      };
+      let text = document.source.slice(&position).to_owned();
+      Chunk {
+        index,
+        breadcrumbs: last.breadcrumbs.clone(),
+        title: title.clone(),
+        size: options.unit.measure(&text),
+        text,
+        position,
+      }
+    })
+    .collect()
+}
+
+/// The children of `node`, with sections replaced by what they contain.
+fn blocks_of(node: &Node) -> Vec<&Node> {
+  let mut nodes = vec![];
+  for child in node.children().into_iter().flatten() {
+    if section::is_section(child) {
+      nodes.extend(blocks_of(child));
+    } else {
+      nodes.push(child);
+    }
+  }
+  nodes
+}
+
+fn add_blocks<'a>(
+  node: &'a Node,
+  breadcrumbs: &[String],
+  size: &dyn Fn(&Position, &Position) -> usize,
+  budget: usize,
+  blocks: &mut Vec<Block<'a>>,
+) {
+  let position = match node.position() {
+    Some(position) => position,
+    None => return,
+  };
+  let splittable = matches!(
+    node,
+    Node::List(_) | Node::ListItem(_) | Node::BlockQuote(_) | Node::FootnoteDefinition(_)
+  );
+  if splittable && size(position, position) > budget {
+    for child in node.children().into_iter().flatten() {
+      add_blocks(child, breadcrumbs, size, budget, blocks);
+    }
+    return;
+  }
+  blocks.push(Block {
+    position,
+    breadcrumbs: breadcrumbs.to_vec(),
+    heading: matches!(node, Node::Heading(_)),
+  });
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{parse_str, Options};
+
+  #[test]
+  fn splits_on_headings_within_budget() {
+    let code = format!("```\n{}\n```", "x".repeat(60));
+    let source = format!(
+      "---\npage_title: Guide\n---\n# Guide\n\n## Install\n\none two\n\nthree four\n\n{}\n\n## Use\n\nfive\n",
+      code
+    );
+    let document = parse_str(&source, &Options::default()).unwrap();
+    let options = ChunkOptions {
+      budget: 40,
+      ..ChunkOptions::default()
+    };
+    let chunks = chunks(&document, &options);
+
+    let texts = chunks.iter().map(|c| c.text.as_str()).collect::<Vec<&str>>();
+    assert_eq!(
+      texts,
+      vec![
+        "# Guide\n\n## Install\n\none two\n\nthree four",
+        code.as_str(),
+        "## Use\n\nfive"
+      ]
+    );
+    assert_eq!(chunks[1].breadcrumbs, vec!["Guide", "Install"]);
+    assert_eq!(chunks[2].breadcrumbs, vec!["Guide", "Use"]);
+    assert_eq!(chunks[0].title.as_deref(), Some("Guide"));
+
+    let position = &chunks[2].position;
+    assert_eq!(&source[position.start.offset..position.end.offset], "## Use\n\nfive");
+  }
+}
@@ -0,0 +1,220 @@
+//! Check that code blocks in structured languages parse.
+//!
+//! Blocks whose `lang` is `json`, `jsonc`, `yaml`, `toml` or `xml` are parsed,
+//! and syntax errors are reported at their position in the markdown file.
+//! A `nocheck` flag in the fence meta turns the check off for one block:
+//!
+//! ````md
+//! ```json nocheck
+//! { "partial": ...
+//! ```
+//! ````
+
+use crate::diagnostic::Diagnostic;
+use crate::source::Source;
+use crate::tangle::parse_meta;
+use crate::visit::{walk, Control};
+use markdown::mdast::{Code, Node};
+use markdown::unist::{Point, Position};
+
+/// A syntax error: its message, and its 1-based line and 0-based character
+/// column in the code.
+type SyntaxError = (String, usize, usize);
+
+/// Syntax errors in the code blocks of `tree`.
+pub fn check(tree: &Node, source: &Source) -> Vec<Diagnostic> {
+  let mut diagnostics = vec![];
+  walk(tree, |node, _| {
+    if let Node::Code(code) = node {
+      let nocheck = code
+        .meta
+        .as_deref()
+        .is_some_and(|meta| parse_meta(meta).iter().any(|(key, _)| key == "nocheck"));
+      if !nocheck {
+        if let Some((message, line, column)) = code.lang.as_deref().and_then(|lang| parse(lang, &code.value)) {
+          let lang = code.lang.as_deref().unwrap_or_default();
+          diagnostics.push(Diagnostic::error(
+            "code",
+            format!("invalid {}: {}", lang, message),
+            locate(code, source, line, column),
+          ));
+        }
+      }
+    }
+    Control::Continue
+  });
+  diagnostics
+}
+
+/// Parse `value` as `lang`; `None` if it is fine or the language is not
+/// checked.
+fn parse(lang: &str, value: &str) -> Option<SyntaxError> {
+  match lang.to_ascii_lowercase().as_str() {
+    "json" => parse_json(value),
+    "jsonc" => parse_json(&strip_jsonc(value)),
+    "yaml" | "yml" => serde_yaml::from_str::<serde_yaml::Value>(value).err().map(|e| {
+      let (line, column) = e.location().map_or((1, 0), |l| (l.line(), l.column().saturating_sub(1)));
+      (strip_location(&e.to_string()), line, column)
+    }),
+    "toml" => value.parse::<toml::Value>().err().map(|e| {
+      let (line, column) = e.line_col().map_or((1, 0), |(line, column)| (line + 1, column));
+      (strip_location(&e.to_string()), line, column)
+    }),
+    "xml" => roxmltree::Document::parse(value).err().map(|e| {
+      let pos = e.pos();
+      (strip_location(&e.to_string()), pos.row as usize, (pos.col as usize).saturating_sub(1))
+    }),
+    _ => None,
+  }
+}
+
+fn parse_json(value: &str) -> Option<SyntaxError> {
+  serde_json::from_str::<serde_json::Value>(value)
+    .err()
+    .map(|e| (strip_location(&e.to_string()), e.line().max(1), e.column().saturating_sub(1)))
+}
+
+/// Messages end in the location, which is reported separately.
+fn strip_location(message: &str) -> String {
+  let end = message
+    .rfind(" at line ")
+    .or_else(|| {
+      let at = message.rfind(" at ")?;
+      let rest = &message[at + 4..];
+      rest
+        .split_once(':')
+        .filter(|(row, col)| row.parse::<u32>().is_ok() && col.parse::<u32>().is_ok())
+        .map(|_| at)
+    })
+    .unwrap_or(message.len());
+  message[..end].to_owned()
+}
+
+/// Replace the comments and trailing commas JSONC allows with spaces, so that
+/// what is left is JSON at the same positions.
+fn strip_jsonc(value: &str) -> String {
+  let mut out = value.as_bytes().to_vec();
+  let bytes = value.as_bytes();
+  let mut index = 0;
+  let mut in_string = false;
+  // where the last comma outside a string is, while only whitespace follows
+  let mut comma = None;
+  while index < bytes.len() {
+    let byte = bytes[index];
+    if in_string {
+      match byte {
+        b'\\' => index += 1,
+        b'"' => in_string = false,
+        _ => {}
+      }
+      index += 1;
+      continue;
+    }
+    match (byte, bytes.get(index + 1)) {
+      (b'/', Some(b'/')) => {
+        while index < bytes.len() && bytes[index] != b'\n' {
+          out[index] = b' ';
+          index += 1;
+        }
+        continue;
+      }
+      (b'/', Some(b'*')) => {
+        let end = value[index + 2..].find("*/").map_or(bytes.len(), |e| index + 2 + e + 2);
+        for at in index..end {
+          if bytes[at] != b'\n' {
+            out[at] = b' ';
+          }
+        }
+        index = end;
+        continue;
+      }
+      (b'"', _) => {
+        in_string = true;
+        comma = None;
+      }
+      (b',', _) => comma = Some(index),
+      (b'}' | b']', _) => {
+        if let Some(at) = comma.take() {
+          out[at] = b' ';
+        }
+      }
+      (byte, _) if byte.is_ascii_whitespace() => {}
+      _ => comma = None,
+    }
+    index += 1;
+  }
+  // only ASCII bytes were replaced, by spaces
+  String::from_utf8(out).unwrap()
+}
+
+/// The point in the file of `column` (in characters) on `line` of the code
+/// in `code`, as a zero-width position.
+fn locate(code: &Code, source: &Source, line: usize, column: usize) -> Option<Position> {
+  let fence = code.position.as_ref()?;
+  let lines = code.value.split('\n').count();
+  // errors at the end of input may be past the last line
+  let (line, column) = if line > lines {
+    (lines, usize::MAX)
+  } else {
+    (line.max(1), column)
+  };
+  let code_line = code.value.split('\n').nth(line - 1).unwrap_or_default();
+  let column = code_line.char_indices().nth(column).map_or(code_line.len(), |(i, _)| i);
+
+  // the line in the file, after the fence; it may have a prefix, such as
+  // the indent of a list item or the `>` of a block quote
+  let file_line = fence.start.line + line;
+  let line_start = source
+    .text
+    .split_inclusive('\n')
+    .take(file_line - 1)
+    .map(|l| l.len())
+    .sum::<usize>();
+  let text = source.text[line_start..].split('\n').next().unwrap_or_default();
+  let prefix = if text.ends_with(code_line) {
+    text.len() - code_line.len()
+  } else {
+    0
+  };
+
+  let offset = source.original_offset(line_start + prefix + column);
+  let point = Point::new(file_line, prefix + column + 1, offset);
+  Some(Position {
+    start: point.clone(),
+    end: point,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{parse_str, Options};
+
+  fn messages(source: &str) -> Vec<String> {
+    let document = parse_str(source, &Options::default()).unwrap();
+    check(&document.tree, &document.source)
+      .into_iter()
+      .map(|d| {
+        let start = d.start().unwrap();
+        format!("{}:{} {}", start.line, start.column, d.message)
+      })
+      .collect()
+  }
+
+  #[test]
+  fn reports_errors_in_the_file() {
+    let source = "# a\n\n```json\n{\n  \"a\": 1,\n}\n```\n\n- item\n\n  ```yaml\n  a: [1\n  ```\n";
+    let found = messages(source);
+    assert_eq!(found.len(), 2);
+    assert!(found[0].starts_with("6:1 invalid json: trailing comma"), "{}", found[0]);
+    assert!(found[1].starts_with("12:") && found[1].contains("invalid yaml"), "{}", found[1]);
+  }
+
+  #[test]
+  fn allows_jsonc_and_nocheck() {
+    let source = "```jsonc\n{\n  // comment\n  \"a\": [1, 2,], /* b */\n}\n```\n\n```toml nocheck\n= nope\n```\n\n```xml\n<a><b></a>\n```\n";
+    let found = messages(source);
+    assert_eq!(found.len(), 1);
+    assert!(found[0].starts_with("13:") && found[0].contains("invalid xml"), "{}", found[0]);
+  }
+}
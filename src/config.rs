@@ -0,0 +1,119 @@
+use crate::chunk::ChunkOptions;
+use crate::html::HtmlOptions;
+use crate::links::LinkOptions;
+use crate::lint::LintOptions;
+use crate::stringify::StringifyOptions;
+use crate::text::TextOptions;
+use crate::toc::TocOptions;
+
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the config file looked up in the working directory when
+/// `--config` is not given.
+pub const DEFAULT_CONFIG_FILE: &str = "markdown-rs-test.json";
+
+/// Settings read from a JSON config file.
+///
+/// Relative paths inside the config are resolved against the directory the
+/// config file lives in.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct Config {
+  /// JSON Schema the frontmatter of every document is validated against.
+  pub schema: Option<PathBuf>,
+  pub syntax: Syntax,
+  /// Built-in transforms to run over every tree, in order; see
+  /// `transform::BUILTINS`.
+  pub transforms: Vec<String>,
+  /// Which headings go in tables of contents.
+  pub toc: TocOptions,
+  /// How documents are split by the `chunk` command.
+  pub chunk: ChunkOptions,
+  /// How plain text is extracted for `--format text`.
+  pub text: TextOptions,
+  /// How the `links` command checks links.
+  pub links: LinkOptions,
+  /// Which rules the `lint` command runs, and how they are reported.
+  pub lint: LintOptions,
+  /// Check that JSON, YAML, TOML and XML code blocks parse; see `code`.
+  pub check_code: bool,
+  /// Style the `fmt` command writes documents in.
+  pub fmt: StringifyOptions,
+  /// How the `html` command renders JSX components and expressions.
+  pub html: HtmlOptions,
+}
+
+/// Syntax extensions to parse, on top of CommonMark.
+#[derive(Deserialize, Debug, Default, Clone, Copy)]
+#[serde(default)]
+pub struct Syntax {
+  /// GFM: autolink literals, footnotes, strikethrough, tables and task lists.
+  pub gfm: bool,
+  /// MDX: ESM, expressions and JSX; turns off HTML, autolinks and indented
+  /// code, which MDX does not support.
+  pub mdx: bool,
+  /// `$` and `$$` math.
+  pub math: bool,
+}
+
+impl Syntax {
+  pub fn parse_options(&self) -> markdown::ParseOptions {
+    let mut constructs = if self.mdx {
+      markdown::Constructs::mdx()
+    } else {
+      markdown::Constructs::default()
+    };
+    if self.gfm {
+      constructs.gfm_autolink_literal = true;
+      constructs.gfm_footnote_definition = true;
+      constructs.gfm_label_start_footnote = true;
+      constructs.gfm_strikethrough = true;
+      constructs.gfm_table = true;
+      constructs.gfm_task_list_item = true;
+    }
+    if self.math {
+      constructs.math_flow = true;
+      constructs.math_text = true;
+    }
+    markdown::ParseOptions {
+      constructs,
+      ..markdown::ParseOptions::default()
+    }
+  }
+}
+
+impl Config {
+  pub fn load(path: &Path) -> Result<Config, String> {
+    let contents =
+      fs::read_to_string(path).map_err(|e| format!("{}: could not read config: {}", path.display(), e))?;
+    let mut config: Config =
+      serde_json::from_str(&contents).map_err(|e| format!("{}: invalid config: {}", path.display(), e))?;
+
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    if let Some(schema) = config.schema.take() {
+      config.schema = Some(base.join(schema));
+    }
+    if let Some(root) = config.links.root.take() {
+      config.links.root = Some(base.join(root));
+    }
+
+    Ok(config)
+  }
+
+  /// Load `path` if given, otherwise the default config file if one exists.
+  pub fn discover(path: Option<&Path>) -> Result<Config, String> {
+    match path {
+      Some(p) => Config::load(p),
+      None => {
+        let default = Path::new(DEFAULT_CONFIG_FILE);
+        if default.exists() {
+          Config::load(default)
+        } else {
+          Ok(Config::default())
+        }
+      }
+    }
+  }
+}
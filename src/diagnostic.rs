@@ -0,0 +1,141 @@
+use crate::wrappers::MyPosition;
+use markdown::unist::{Point, Position};
+
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use std::fmt;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+  Warning,
+  Error,
+}
+
+impl fmt::Display for Severity {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Severity::Warning => write!(f, "warning"),
+      Severity::Error => write!(f, "error"),
+    }
+  }
+}
+
+/// A message about a document, pointing into the original source file.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+  pub severity: Severity,
+  /// The check that produced this diagnostic, e.g. `schema`.
+  pub source: String,
+  pub message: String,
+  pub position: Option<Position>,
+  /// Edits to the file that fix the problem; empty if it cannot be fixed
+  /// automatically.
+  pub fix: Vec<Edit>,
+}
+
+/// Replace the bytes `start..end` of the file on disk with `text`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Edit {
+  pub start: usize,
+  pub end: usize,
+  pub text: String,
+}
+
+impl Diagnostic {
+  pub fn error(source: &str, message: String, position: Option<Position>) -> Diagnostic {
+    Diagnostic {
+      severity: Severity::Error,
+      source: source.to_owned(),
+      message,
+      position,
+      fix: vec![],
+    }
+  }
+
+  pub fn warning(source: &str, message: String, position: Option<Position>) -> Diagnostic {
+    Diagnostic {
+      severity: Severity::Warning,
+      source: source.to_owned(),
+      message,
+      position,
+      fix: vec![],
+    }
+  }
+
+  pub fn start(&self) -> Option<&Point> {
+    self.position.as_ref().map(|p| &p.start)
+  }
+}
+
+impl Serialize for Diagnostic {
+  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    let mut state = serializer.serialize_struct("Diagnostic", 5)?;
+    state.serialize_field("severity", &self.severity)?;
+    state.serialize_field("source", &self.source)?;
+    state.serialize_field("message", &self.message)?;
+    if let Some(p) = self.position.clone() {
+      let pos: MyPosition = MyPosition::from(p);
+      state.serialize_field("position", &pos)?;
+    }
+    if !self.fix.is_empty() {
+      state.serialize_field("fix", &self.fix)?;
+    }
+    state.end()
+  }
+}
+
+/// Diagnostics of every file processed in one run.
+#[derive(Debug, Default)]
+pub struct Report {
+  pub files: Vec<(PathBuf, Vec<Diagnostic>)>,
+}
+
+impl Report {
+  pub fn add(&mut self, file: PathBuf, diagnostics: Vec<Diagnostic>) {
+    self.files.push((file, diagnostics));
+  }
+
+  pub fn count(&self, severity: Severity) -> usize {
+    self
+      .files
+      .iter()
+      .flat_map(|(_, d)| d.iter())
+      .filter(|d| d.severity == severity)
+      .count()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.files.iter().all(|(_, d)| d.is_empty())
+  }
+
+  pub fn has_errors(&self) -> bool {
+    self.count(Severity::Error) > 0
+  }
+}
+
+impl fmt::Display for Report {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (file, diagnostics) in &self.files {
+      for d in diagnostics {
+        match d.start() {
+          Some(p) => write!(f, "{}:{}:{}", file.display(), p.line, p.column)?,
+          None => write!(f, "{}", file.display())?,
+        }
+        writeln!(f, ": {}: {} [{}]", d.severity, d.message, d.source)?;
+      }
+    }
+    let failed = self.files.iter().filter(|(_, d)| !d.is_empty()).count();
+    write!(
+      f,
+      "{} error(s), {} warning(s) in {} of {} file(s)",
+      self.count(Severity::Error),
+      self.count(Severity::Warning),
+      failed,
+      self.files.len()
+    )
+  }
+}
@@ -0,0 +1,260 @@
+//! Comments that turn lint rules off for part of a document.
+//!
+//! ```md
+//! <!-- lint disable no-bare-urls -->
+//! <!-- lint enable no-bare-urls -->
+//! <!-- lint ignore-next-line -->
+//! ```
+//!
+//! Without rule names a directive applies to every rule. In MDX, where HTML
+//! comments are not allowed, the same directives go in expression comments:
+//! `{/* lint disable */}`.
+//!
+//! A comment before the content of a document that starts with "This file
+//! is generated", such as `<!-- This file is generated through yarn
+//! generate-docs -->`, or that has an `@generated` token marks the whole
+//! document as generated.
+
+use crate::diagnostic::Diagnostic;
+use crate::visit::{walk, Control};
+use markdown::mdast::Node;
+use markdown::unist::Position;
+
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Kind {
+  Disable,
+  Enable,
+  IgnoreNextLine,
+}
+
+/// A `lint` comment.
+#[derive(Debug, Clone)]
+pub struct Directive {
+  pub kind: Kind,
+  /// Rules it applies to; every rule when empty.
+  pub rules: Vec<String>,
+  /// Of the comment.
+  pub position: Option<Position>,
+}
+
+/// The directives of a document.
+#[derive(Debug, Default)]
+pub struct Directives {
+  /// In document order.
+  pub directives: Vec<Directive>,
+  /// Lines `ignore-next-line` directives apply to, with their rules.
+  lines: Vec<(usize, Vec<String>)>,
+  /// Whether the document starts with a "generated" marker.
+  pub generated: bool,
+  /// Comments that start with `lint` but are not a directive.
+  pub invalid: Vec<Diagnostic>,
+}
+
+impl Directives {
+  /// The directives in `tree`, parsed from `text`. Documents read them
+  /// before transforms run, as those may remove comments.
+  pub fn of(tree: &Node, text: &str) -> Directives {
+    let mut directives = Directives::default();
+    let mut content_started = false;
+    walk(tree, |node, context| {
+      let (comment, position) = match node {
+        Node::Html(n) => (html_comment(&n.value), &n.position),
+        Node::MdxFlowExpression(n) => (expression_comment(&n.value), &n.position),
+        Node::MdxTextExpression(n) => (expression_comment(&n.value), &n.position),
+        Node::Root(_) => return Control::Continue,
+        Node::Yaml(_) | Node::Toml(_) | Node::MdxjsEsm(_) => return Control::Skip,
+        _ => {
+          content_started |= context.ancestors.len() == 1;
+          return Control::Continue;
+        }
+      };
+      let comment = match comment {
+        Some(comment) => comment,
+        None => {
+          content_started |= context.ancestors.len() == 1;
+          return Control::Continue;
+        }
+      };
+      if !content_started && is_generated_marker(comment) {
+        directives.generated = true;
+      }
+
+      let mut words = comment
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|w| !w.is_empty());
+      if words.next() != Some("lint") {
+        return Control::Continue;
+      }
+      let kind = match words.next() {
+        Some("disable") => Kind::Disable,
+        Some("enable") => Kind::Enable,
+        Some("ignore-next-line") => Kind::IgnoreNextLine,
+        _ => {
+          directives.invalid.push(Diagnostic::warning(
+            "lint",
+            format!(
+              "unknown lint directive `{}`, expected `disable`, `enable` or `ignore-next-line`",
+              comment
+            ),
+            position.clone(),
+          ));
+          return Control::Continue;
+        }
+      };
+      let rules = words.map(str::to_owned).collect::<Vec<String>>();
+      if kind == Kind::IgnoreNextLine {
+        if let Some(position) = position {
+          let line = next_line(text, position.end.line);
+          directives.lines.push((line, rules.clone()));
+        }
+      }
+      directives.directives.push(Directive {
+        kind,
+        rules,
+        position: position.clone(),
+      });
+      Control::Continue
+    });
+    directives
+  }
+
+  /// Whether `diagnostic` is turned off by a directive.
+  pub fn suppresses(&self, diagnostic: &Diagnostic) -> bool {
+    let start = match diagnostic.start() {
+      Some(start) => start,
+      None => return false,
+    };
+    let applies = |rules: &[String]| rules.is_empty() || rules.contains(&diagnostic.source);
+    if self
+      .lines
+      .iter()
+      .any(|(line, rules)| *line == start.line && applies(rules))
+    {
+      return true;
+    }
+
+    // replay the directives before the diagnostic
+    let mut all = false;
+    let mut disabled = HashSet::new();
+    let mut enabled = HashSet::new();
+    for directive in &self.directives {
+      match &directive.position {
+        Some(p) if p.end.offset <= start.offset => {}
+        _ => continue,
+      }
+      match (&directive.kind, directive.rules.is_empty()) {
+        (Kind::Disable, true) => {
+          all = true;
+          disabled.clear();
+          enabled.clear();
+        }
+        (Kind::Enable, true) => {
+          all = false;
+          disabled.clear();
+          enabled.clear();
+        }
+        (Kind::Disable, false) => {
+          for rule in &directive.rules {
+            enabled.remove(rule);
+            disabled.insert(rule);
+          }
+        }
+        (Kind::Enable, false) => {
+          for rule in &directive.rules {
+            disabled.remove(rule);
+            enabled.insert(rule);
+          }
+        }
+        (Kind::IgnoreNextLine, _) => {}
+      }
+    }
+    if all {
+      !enabled.contains(&diagnostic.source)
+    } else {
+      disabled.contains(&diagnostic.source)
+    }
+  }
+}
+
+/// Whether `comment` says that its file is generated.
+fn is_generated_marker(comment: &str) -> bool {
+  comment.split_whitespace().any(|word| word == "@generated")
+    || comment.to_lowercase().starts_with("this file is generated")
+}
+
+/// The text of `value` if it is one HTML comment.
+fn html_comment(value: &str) -> Option<&str> {
+  let inner = value.trim().strip_prefix("<!--")?.strip_suffix("-->")?;
+  (!inner.contains("-->")).then(|| inner.trim())
+}
+
+/// The text of `value` if it is one JavaScript block comment.
+fn expression_comment(value: &str) -> Option<&str> {
+  let inner = value.trim().strip_prefix("/*")?.strip_suffix("*/")?;
+  (!inner.contains("*/")).then(|| inner.trim())
+}
+
+/// The first line after `line` with something on it.
+fn next_line(text: &str, line: usize) -> usize {
+  text
+    .split('\n')
+    .enumerate()
+    .skip(line)
+    .find(|(_, l)| !l.trim().is_empty())
+    .map_or(line + 1, |(index, _)| index + 1)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{parse_str, Options};
+
+  #[test]
+  fn reads_directives() {
+    let source = "<!-- This file is generated through yarn generate-docs -->\n\n# A\n\n\
+      <!-- lint disable no-bare-urls, single-h1 -->\n\n<!-- lint ignore-next-line -->\n\npara\n\n\
+      <!-- lint enable single-h1 -->\n\n<!-- lint off -->\n";
+    let document = parse_str(source, &Options::default()).unwrap();
+    let directives = &document.directives;
+    assert!(directives.generated);
+    assert_eq!(directives.directives.len(), 3);
+    assert_eq!(directives.invalid.len(), 1);
+
+    let at = |source: &str, line: usize| {
+      let point = markdown::unist::Point::new(
+        line,
+        1,
+        document
+          .source
+          .text
+          .split('\n')
+          .take(line - 1)
+          .map(|l| l.len() + 1)
+          .sum(),
+      );
+      Diagnostic::warning(
+        source,
+        String::new(),
+        Some(Position {
+          start: point.clone(),
+          end: point,
+        }),
+      )
+    };
+    assert!(!directives.suppresses(&at("no-bare-urls", 3)));
+    assert!(directives.suppresses(&at("no-bare-urls", 9)));
+    assert!(directives.suppresses(&at("heading-increment", 9)));
+    assert!(!directives.suppresses(&at("heading-increment", 11)));
+    assert!(directives.suppresses(&at("single-h1", 10)));
+    assert!(!directives.suppresses(&at("single-h1", 13)));
+    assert!(directives.suppresses(&at("no-bare-urls", 13)));
+
+    let generated = |source: &str| parse_str(source, &Options::default()).unwrap().directives.generated;
+    assert!(generated("<!-- @generated by docgen -->\n\n# A\n"));
+    assert!(!generated("# A\n\n<!-- This file is generated -->\n"));
+    assert!(!generated("<!-- not generated -->\n\n# A\n"));
+    assert!(!generated("<!-- regenerated headings below -->\n\n# A\n"));
+  }
+}
@@ -0,0 +1,95 @@
+//! Rewrite documents in one style, for the `fmt` command.
+//!
+//! The content is parsed and written back with `stringify`. Frontmatter is
+//! copied from the file as it is, and the output keeps the encoding, byte
+//! order mark and line endings of the file.
+
+use crate::frontmatter;
+use crate::source::Source;
+use crate::stringify::{to_markdown, StringifyOptions};
+
+use markdown::ParseOptions;
+use similar::TextDiff;
+
+/// `bytes`, the contents of a file, formatted.
+pub fn format(bytes: &[u8], parse: &ParseOptions, options: &StringifyOptions) -> Result<Vec<u8>, String> {
+  let source = Source::decode(bytes)?;
+  let split = frontmatter::split(&source.text)?;
+  let tree = markdown::to_mdast(split.content, parse)?;
+  let body = to_markdown(&tree, &parse.constructs, options);
+
+  // everything up to the end of the frontmatter, or the byte order mark
+  let (end, text) = match &split.frontmatter {
+    Some(frontmatter) if body.is_empty() => (frontmatter.position.end.offset, "\n".to_owned()),
+    Some(frontmatter) => (frontmatter.position.end.offset, format!("\n\n{}", body)),
+    None => (0, body),
+  };
+  let mut output = bytes[..source.original_offset(end)].to_vec();
+  output.extend(source.encode(&text, end));
+  Ok(output)
+}
+
+/// A unified diff from `original` to `formatted`, both contents of the file
+/// at `name`.
+pub fn diff(name: &str, original: &[u8], formatted: &[u8]) -> String {
+  let text = |bytes: &[u8]| match Source::decode(bytes) {
+    Ok(source) => source.text,
+    Err(_) => String::from_utf8_lossy(bytes).into_owned(),
+  };
+  let (original, formatted) = (text(original), text(formatted));
+  TextDiff::from_lines(&original, &formatted)
+    .unified_diff()
+    .header(name, name)
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::Syntax;
+  use crate::stringify::{HeadingStyle, ProseWrap};
+
+  #[test]
+  fn keeps_frontmatter_and_encoding() {
+    let source = "---\r\ntitle:   'A'\r\n---\r\n\r\n\r\nTitle\r\n=====\r\n\r\n* a\r\n* b\r\n";
+    let options = StringifyOptions {
+      heading: HeadingStyle::Atx,
+      ..StringifyOptions::default()
+    };
+    let output = format(source.as_bytes(), &Syntax::default().parse_options(), &options).unwrap();
+    assert_eq!(
+      String::from_utf8(output).unwrap(),
+      "---\r\ntitle:   'A'\r\n---\r\n\r\n# Title\r\n\r\n- a\r\n- b\r\n"
+    );
+
+    let bom = b"\xEF\xBB\xBF_a_\n";
+    let output = format(bom, &Syntax::default().parse_options(), &options).unwrap();
+    assert_eq!(output, b"\xEF\xBB\xBF*a*\n");
+  }
+
+  #[test]
+  fn wraps_prose() {
+    let source = "> A paragraph with words that go on\n> for a while - and a list:\n\n- one two three four five six\n";
+    let parse = Syntax::default().parse_options();
+    let wrap = |prose_wrap, width| {
+      let options = StringifyOptions {
+        prose_wrap,
+        width,
+        ..StringifyOptions::default()
+      };
+      String::from_utf8(format(source.as_bytes(), &parse, &options).unwrap()).unwrap()
+    };
+    assert_eq!(
+      wrap(ProseWrap::Always, 20),
+      "> A paragraph with\n> words that go on\n> for a while - and\n> a list:\n\n- one two three four\n  five six\n"
+    );
+    assert_eq!(
+      wrap(ProseWrap::Never, 20),
+      "> A paragraph with words that go on for a while - and a list:\n\n- one two three four five six\n"
+    );
+    assert_eq!(wrap(ProseWrap::Preserve, 20), source);
+
+    let changes = diff("a.md", b"* a\n", b"- a\n");
+    assert!(changes.contains("--- a.md\n+++ a.md\n") && changes.contains("-* a\n+- a\n"), "{}", changes);
+  }
+}
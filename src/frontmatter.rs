@@ -0,0 +1,598 @@
+use crate::diagnostic::Diagnostic;
+use crate::wrappers::MyPosition;
+use markdown::unist::{Point, Position};
+
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use serde_json::Value;
+
+// Frontmatter is the metadata block at the very start of a document:
+//
+// * YAML, fenced by `---` (Jekyll, Gatsby, Next.js)
+// * TOML, fenced by `+++` (Hugo)
+// * JSON, a bare object whose opening `{` is on the first line (Hugo)
+//
+// All three are parsed into the same `serde_json::Value`.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+  Yaml,
+  Toml,
+  Json,
+}
+
+#[derive(Debug, Clone)]
+pub struct Frontmatter {
+  pub format: Format,
+  /// Text between the fences; for JSON, the object itself.
+  pub raw: String,
+  pub data: Value,
+  /// The whole block, including fences.
+  pub position: Position,
+  /// Where `raw` starts in the source.
+  raw_start: Point,
+}
+
+impl Serialize for Frontmatter {
+  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    let mut state = serializer.serialize_struct("Frontmatter", 4)?;
+    state.serialize_field("format", &self.format)?;
+    state.serialize_field("raw", &self.raw)?;
+    state.serialize_field("data", &self.data)?;
+    let pos: MyPosition = MyPosition::from(self.position.clone());
+    state.serialize_field("position", &pos)?;
+    state.end()
+  }
+}
+
+/// A source split into its frontmatter and the markdown after it.
+#[derive(Debug)]
+pub struct Split<'a> {
+  pub frontmatter: Option<Frontmatter>,
+  pub content: &'a str,
+  /// Where `content` starts in the source.
+  pub content_start: Point,
+  /// Why the frontmatter could not be parsed, from `split_lenient`.
+  pub error: Option<Diagnostic>,
+}
+
+/// One step of a path into frontmatter data.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+  Key(String),
+  Index(usize),
+}
+
+struct Line<'a> {
+  number: usize,
+  offset: usize,
+  text: &'a str,
+}
+
+fn lines(source: &str) -> Vec<Line<'_>> {
+  let mut offset = 0;
+  source
+    .split_inclusive('\n')
+    .enumerate()
+    .map(|(index, text)| {
+      let line = Line {
+        number: index + 1,
+        offset,
+        text: text.trim_end_matches(&['\r', '\n'][..]),
+      };
+      offset += text.len();
+      line
+    })
+    .collect()
+}
+
+/// Split the frontmatter off `source`, if there is any.
+///
+/// A JSON object that fails to parse is not an error: in MDX, a `{` on the
+/// first line may just as well start an expression, so it is left as content.
+pub fn split(source: &str) -> Result<Split<'_>, String> {
+  let split = split_lenient(source);
+  match &split.error {
+    Some(error) => {
+      let at = error.start().unwrap();
+      Err(format!("{}:{}: {}", at.line, at.column, error.message))
+    }
+    None => Ok(split),
+  }
+}
+
+/// Like `split`, but YAML or TOML frontmatter that fails to parse is left
+/// out and reported in `Split::error`; the content after it is still split
+/// off.
+pub fn split_lenient(source: &str) -> Split<'_> {
+  let lines = lines(source);
+  let first = match lines.first() {
+    Some(line) => line.text.trim_end(),
+    None => "",
+  };
+
+  let (format, fence) = match first {
+    "---" => (Format::Yaml, "---"),
+    "+++" => (Format::Toml, "+++"),
+    _ if first.starts_with('{') => return split_json(source),
+    _ => return no_frontmatter(source),
+  };
+
+  let close = lines.iter().skip(1).find(|l| {
+    let text = l.text.trim_end();
+    text == fence || (format == Format::Yaml && text == "...")
+  });
+  let close = match close {
+    Some(l) => l,
+    None => return no_frontmatter(source),
+  };
+
+  let raw_start = Point::new(2, 1, lines[1].offset);
+  let raw = &source[raw_start.offset..close.offset];
+  let block_end = close.offset + close.text.len();
+  let content_offset = (block_end + 1).min(source.len());
+
+  let content = &source[content_offset..];
+  let content_start = point_at(source, content_offset);
+
+  let parsed = match format {
+    Format::Yaml => parse_yaml(raw),
+    _ => parse_toml(raw),
+  };
+  let data = match parsed {
+    Ok(data) => data,
+    Err((offset, message)) => {
+      let at = point_at(source, raw_start.offset + offset);
+      let message = format!("invalid {:?} frontmatter: {}", format, message);
+      return Split {
+        frontmatter: None,
+        content,
+        content_start,
+        error: Some(Diagnostic::error("frontmatter", message, Some(Position { start: at.clone(), end: at }))),
+      };
+    }
+  };
+
+  Split {
+    frontmatter: Some(Frontmatter {
+      format,
+      raw: raw.to_owned(),
+      data,
+      position: Position {
+        start: Point::new(1, 1, 0),
+        end: Point::new(close.number, close.text.len() + 1, block_end),
+      },
+      raw_start,
+    }),
+    content,
+    content_start,
+    error: None,
+  }
+}
+
+fn no_frontmatter(source: &str) -> Split<'_> {
+  Split {
+    frontmatter: None,
+    content: source,
+    content_start: Point::new(1, 1, 0),
+    error: None,
+  }
+}
+
+fn split_json(source: &str) -> Split<'_> {
+  let mut stream = serde_json::Deserializer::from_str(source).into_iter::<Value>();
+  let data = match stream.next() {
+    Some(Ok(data @ Value::Object(_))) => data,
+    _ => return no_frontmatter(source),
+  };
+  let end = stream.byte_offset();
+
+  // The object has to be alone on its last line.
+  let rest = &source[end..];
+  let line_end = rest.find('\n').map_or(source.len(), |i| end + i);
+  if !source[end..line_end].trim().is_empty() {
+    return no_frontmatter(source);
+  }
+  let content_offset = (line_end + 1).min(source.len());
+
+  Split {
+    frontmatter: Some(Frontmatter {
+      format: Format::Json,
+      raw: source[..end].to_owned(),
+      data,
+      position: Position {
+        start: Point::new(1, 1, 0),
+        end: point_at(source, end),
+      },
+      raw_start: Point::new(1, 1, 0),
+    }),
+    content: &source[content_offset..],
+    content_start: point_at(source, content_offset),
+    error: None,
+  }
+}
+
+fn parse_yaml(raw: &str) -> Result<Value, (usize, String)> {
+  if raw.trim().is_empty() {
+    return Ok(Value::Object(Default::default()));
+  }
+  match serde_yaml::from_str::<Value>(raw) {
+    Ok(Value::Null) => Ok(Value::Object(Default::default())),
+    Ok(value) => Ok(value),
+    Err(e) => Err((e.location().map_or(0, |l| l.index()), e.to_string())),
+  }
+}
+
+fn parse_toml(raw: &str) -> Result<Value, (usize, String)> {
+  match raw.parse::<toml::Value>() {
+    Ok(value) => Ok(toml_to_json(value)),
+    Err(e) => {
+      let offset = e.line_col().map_or(0, |(line, column)| {
+        lines(raw).get(line).map_or(raw.len(), |l| l.offset + column)
+      });
+      Err((offset, e.to_string()))
+    }
+  }
+}
+
+fn toml_to_json(value: toml::Value) -> Value {
+  match value {
+    toml::Value::String(s) => Value::String(s),
+    toml::Value::Integer(i) => Value::from(i),
+    toml::Value::Float(f) => Value::from(f),
+    toml::Value::Boolean(b) => Value::Bool(b),
+    toml::Value::Datetime(d) => Value::String(d.to_string()),
+    toml::Value::Array(a) => Value::Array(a.into_iter().map(toml_to_json).collect()),
+    toml::Value::Table(t) => Value::Object(t.into_iter().map(|(k, v)| (k, toml_to_json(v))).collect()),
+  }
+}
+
+/// The point of byte `offset` in `source`.
+pub fn point_at(source: &str, offset: usize) -> Point {
+  let before = &source[..offset];
+  let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+  Point::new(
+    before.matches('\n').count() + 1,
+    offset - line_start + 1,
+    offset,
+  )
+}
+
+impl Frontmatter {
+  /// Find the position of the value at `path` in the source.
+  ///
+  /// When a step cannot be found, for example inside a YAML flow collection
+  /// or a TOML inline table, the position of the deepest value that was found
+  /// is returned, falling back to the opening fence.
+  pub fn locate(&self, path: &[Segment]) -> Position {
+    let found = match self.format {
+      Format::Yaml => locate_yaml(&self.raw, path),
+      Format::Toml => locate_toml(&self.raw, path),
+      Format::Json => locate_json(&self.raw, path),
+    };
+
+    match found {
+      Some((start, end)) => {
+        let start = point_at(&self.raw, start);
+        let end = point_at(&self.raw, end);
+        Position {
+          start: self.shift(start),
+          end: self.shift(end),
+        }
+      }
+      None => {
+        // the opening fence
+        let fence = if self.format == Format::Json { 1 } else { 3 };
+        Position {
+          start: Point::new(1, 1, 0),
+          end: Point::new(1, fence + 1, fence),
+        }
+      }
+    }
+  }
+
+  fn shift(&self, point: Point) -> Point {
+    let column = if point.line == 1 {
+      point.column + self.raw_start.column - 1
+    } else {
+      point.column
+    };
+    Point::new(
+      point.line + self.raw_start.line - 1,
+      column,
+      point.offset + self.raw_start.offset,
+    )
+  }
+}
+
+fn indent(text: &str) -> usize {
+  text.len() - text.trim_start().len()
+}
+
+fn is_blank(text: &str) -> bool {
+  let trimmed = text.trim();
+  trimmed.is_empty() || trimmed.starts_with('#')
+}
+
+/// Byte range of the content of `line`, without its indentation.
+fn span(line: &Line) -> (usize, usize) {
+  (line.offset + indent(line.text), line.offset + line.text.len())
+}
+
+// YAML block collections are followed by indentation: the children of a line
+// are the more indented lines after it.
+fn locate_yaml(raw: &str, path: &[Segment]) -> Option<(usize, usize)> {
+  let lines = lines(raw);
+  let mut found = None;
+  let mut scope = &lines[..];
+  let mut parent_indent: Option<usize> = None;
+
+  for segment in path {
+    let level = match scope
+      .iter()
+      .filter(|l| !is_blank(l.text))
+      .map(|l| indent(l.text))
+      .find(|i| parent_indent.is_none_or(|p| *i > p))
+    {
+      Some(i) => i,
+      None => break,
+    };
+
+    let mut items = 0;
+    let hit = scope.iter().position(|l| {
+      if is_blank(l.text) || indent(l.text) != level {
+        return false;
+      }
+      let rest = &l.text[level..];
+      match segment {
+        Segment::Key(key) => {
+          rest.starts_with(&format!("{}:", key))
+            || rest.starts_with(&format!("\"{}\":", key))
+            || rest.starts_with(&format!("'{}':", key))
+        }
+        Segment::Index(n) => {
+          if rest == "-" || rest.starts_with("- ") {
+            items += 1;
+            items == n + 1
+          } else {
+            false
+          }
+        }
+      }
+    });
+
+    let index = match hit {
+      Some(i) => i,
+      None => break,
+    };
+    found = Some(span(&scope[index]));
+
+    let rest = &scope[index + 1..];
+    let end = rest
+      .iter()
+      .position(|l| !is_blank(l.text) && indent(l.text) <= level)
+      .unwrap_or(rest.len());
+    scope = &rest[..end];
+    parent_indent = Some(level);
+  }
+
+  found
+}
+
+/// Length of the longest common prefix of two paths.
+fn common(a: &[Segment], b: &[Segment]) -> usize {
+  a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+fn toml_key(key: &str) -> Vec<Segment> {
+  key
+    .split('.')
+    .map(|k| Segment::Key(k.trim().trim_matches(&['"', '\''][..]).to_owned()))
+    .collect()
+}
+
+// TOML keys are found by tracking the `[table]` and `[[array]]` headers and
+// the dotted keys below them; the entry sharing the longest prefix wins.
+fn locate_toml(raw: &str, path: &[Segment]) -> Option<(usize, usize)> {
+  let mut table: Vec<Segment> = vec![];
+  let mut arrays: Vec<(Vec<Segment>, usize)> = vec![];
+  let mut best: Option<(usize, (usize, usize))> = None;
+
+  for line in lines(raw) {
+    let text = line.text.trim();
+    let entry = if text.starts_with("[[") && text.ends_with("]]") {
+      let name = toml_key(&text[2..text.len() - 2]);
+      let count = match arrays.iter_mut().find(|(n, _)| *n == name) {
+        Some((_, count)) => {
+          *count += 1;
+          *count
+        }
+        None => {
+          arrays.push((name.clone(), 0));
+          0
+        }
+      };
+      table = name;
+      table.push(Segment::Index(count));
+      table.clone()
+    } else if text.starts_with('[') && text.ends_with(']') {
+      table = toml_key(&text[1..text.len() - 1]);
+      table.clone()
+    } else if let Some(eq) = text.find('=').filter(|_| !is_blank(text)) {
+      let mut key = table.clone();
+      key.extend(toml_key(&text[..eq]));
+      key
+    } else {
+      continue;
+    };
+
+    let length = common(&entry, path);
+    if length == entry.len() && best.is_none_or(|(b, _)| length > b) {
+      best = Some((length, span(&line)));
+    }
+  }
+
+  best.filter(|(length, _)| *length > 0).map(|(_, span)| span)
+}
+
+// JSON is scanned once, recording where every member and item starts.
+fn locate_json(raw: &str, path: &[Segment]) -> Option<(usize, usize)> {
+  enum Container {
+    Object(Option<String>),
+    Array(usize),
+  }
+
+  let bytes = raw.as_bytes();
+  let mut stack: Vec<Container> = vec![];
+  let mut best: Option<(usize, (usize, usize))> = None;
+  let mut index = 0;
+
+  let mut record = |stack: &Vec<Container>, start: usize, end: usize| {
+    let entry = stack
+      .iter()
+      .map(|c| match c {
+        Container::Object(key) => Segment::Key(key.clone().unwrap_or_default()),
+        Container::Array(i) => Segment::Index(*i),
+      })
+      .collect::<Vec<Segment>>();
+    let length = common(&entry, path);
+    if length == entry.len() && best.is_none_or(|(b, _)| length > b) {
+      best = Some((length, (start, end)));
+    }
+  };
+
+  while index < bytes.len() {
+    match bytes[index] {
+      b'"' => {
+        let start = index;
+        index += 1;
+        while index < bytes.len() && bytes[index] != b'"' {
+          index += if bytes[index] == b'\\' { 2 } else { 1 };
+        }
+        let end = (index + 1).min(bytes.len());
+        let is_key = raw[end..].trim_start().starts_with(':');
+        if let (true, Some(Container::Object(key))) = (is_key, stack.last_mut()) {
+          *key = serde_json::from_str(&raw[start..end]).ok();
+          let line_end = raw[start..].find('\n').map_or(raw.len(), |i| start + i);
+          record(&stack, start, line_end.max(end));
+        }
+      }
+      b'{' => stack.push(Container::Object(None)),
+      b'[' => {
+        stack.push(Container::Array(0));
+        record(&stack, index + 1, index + 1);
+      }
+      b'}' | b']' => {
+        stack.pop();
+      }
+      b',' => {
+        if let Some(Container::Array(i)) = stack.last_mut() {
+          *i += 1;
+          let start = index + 1 + (raw[index + 1..].len() - raw[index + 1..].trim_start().len());
+          record(&stack, start, start);
+        }
+      }
+      _ => {}
+    }
+    index += 1;
+  }
+
+  best.filter(|(length, _)| *length > 0).map(|(_, span)| span)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn key(k: &str) -> Segment {
+    Segment::Key(k.to_owned())
+  }
+
+  #[test]
+  fn detects_formats() {
+    let yaml = split("---\ntitle: a\n---\n# Hi\n").unwrap();
+    let toml = split("+++\ntitle = \"a\"\n+++\n# Hi\n").unwrap();
+    let json = split("{\n  \"title\": \"a\"\n}\n# Hi\n").unwrap();
+    let one_line = split("{ \"title\": \"a\" }\n# Hi\n").unwrap();
+
+    for (split, format) in [
+      (yaml, Format::Yaml),
+      (toml, Format::Toml),
+      (json, Format::Json),
+      (one_line, Format::Json),
+    ] {
+      let frontmatter = split.frontmatter.unwrap();
+      assert_eq!(frontmatter.format, format);
+      assert_eq!(frontmatter.data["title"], "a");
+      assert_eq!(split.content, "# Hi\n");
+      assert_eq!(split.content_start.line, frontmatter.position.end.line + 1);
+    }
+  }
+
+  #[test]
+  fn leaves_expressions_alone() {
+    let multi_line = split("{\n  props.title\n}\n").unwrap();
+    assert!(multi_line.frontmatter.is_none());
+    assert_eq!(multi_line.content, "{\n  props.title\n}\n");
+
+    let one_line = split("{props.title} is here\n").unwrap();
+    assert!(one_line.frontmatter.is_none());
+  }
+
+  #[test]
+  fn reports_broken_frontmatter() {
+    use crate::testing::TempDir;
+    use crate::{collect_files, parse_file, Options};
+
+    let dir = TempDir::new("frontmatter");
+    dir.write("a.md", "---\ntitle: [a\n---\n# A\n");
+    dir.write("b.md", "+++\ntitle = \"b\"\n+++\n# B\n");
+    let files = collect_files(&[dir.path().to_owned()]).unwrap();
+    let documents = files
+      .iter()
+      .map(|file| parse_file(file, &Options::default()))
+      .collect::<Result<Vec<_>, String>>()
+      .unwrap();
+
+    let broken = &documents[0];
+    assert!(broken.frontmatter.is_none());
+    assert_eq!(broken.diagnostics.len(), 1);
+    assert!(broken.diagnostics[0].message.starts_with("invalid Yaml frontmatter"));
+    assert_eq!(broken.diagnostics[0].start().unwrap().line, 3);
+    assert_eq!(broken.ids, vec!["a"]);
+    assert_eq!(broken.tree.children().unwrap()[0].position().unwrap().start.line, 4);
+
+    let good = &documents[1];
+    assert_eq!(good.frontmatter.as_ref().unwrap().data["title"], "b");
+    assert!(good.diagnostics.is_empty());
+
+    assert!(split("---\ntitle: [a\n---\n").is_err());
+  }
+
+  #[test]
+  fn locates_keys_and_items() {
+    let source = "---\npage_title: A title\ntags:\n  - one\n  - two\nnested:\n  inner: 1\n---\n";
+    let frontmatter = split(source).unwrap().frontmatter.unwrap();
+
+    let title = frontmatter.locate(&[key("page_title")]);
+    assert_eq!((title.start.line, title.start.column), (2, 1));
+    assert_eq!(&source[title.start.offset..title.end.offset], "page_title: A title");
+    let tag = frontmatter.locate(&[key("tags"), Segment::Index(1)]);
+    assert_eq!((tag.start.line, tag.start.column), (5, 3));
+    let inner = frontmatter.locate(&[key("nested"), key("inner")]);
+    assert_eq!((inner.start.line, inner.start.column), (7, 3));
+
+    let source = "+++\ntitle = \"a\"\n[params]\nauthor = \"b\"\n+++\n";
+    let frontmatter = split(source).unwrap().frontmatter.unwrap();
+    let author = frontmatter.locate(&[key("params"), key("author")]);
+    assert_eq!(&source[author.start.offset..author.end.offset], "author = \"b\"");
+
+    let source = "{\n  \"title\": \"a\",\n  \"tags\": [\"x\", \"y\"]\n}\n";
+    let frontmatter = split(source).unwrap().frontmatter.unwrap();
+    let tag = frontmatter.locate(&[key("tags"), Segment::Index(1)]);
+    assert_eq!(&source[tag.start.offset..tag.start.offset + 3], "\"y\"");
+  }
+}
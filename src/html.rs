@@ -0,0 +1,710 @@
+//! Render documents to HTML, for static previews.
+//!
+//! Markdown renders as `markdown::to_html` would render it, with GFM
+//! tables, task lists and footnotes, math as `language-math` code, and ids
+//! on headings. JSX elements render through templates keyed by component
+//! name, in which `{children}` is the rendered content, `{component}` the
+//! name, and any other `{name}` the value of that attribute:
+//!
+//! ```json
+//! {
+//!   "html": {
+//!     "components": {
+//!       "Note": "<aside class=\"note note-{type}\">{children}</aside>"
+//!     }
+//!   }
+//! }
+//! ```
+//!
+//! Components without a template use the `fallback` one. Lowercase names,
+//! such as `<div>`, are HTML elements and render as written. Expressions
+//! cannot be evaluated, so they render as placeholders.
+//!
+//! Like `markdown::to_html`, HTML in markdown is escaped and URLs with
+//! protocols other than safe ones are dropped, unless `allow_dangerous_html`
+//! and `allow_dangerous_protocol` say otherwise. For the same reason JSX
+//! `script`, `style`, `iframe`, `object` and `embed` elements are left out,
+//! and so are `on*` and `style` attributes.
+
+use crate::resolve::normalize_identifier;
+use crate::section;
+use crate::visit::{walk, Control};
+use crate::Document;
+use markdown::mdast::{AlignKind, AttributeContent, AttributeValue, List, Node, Table};
+
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct HtmlOptions {
+  /// Templates of components, by name, e.g. `Note` or `Tabs.Item`.
+  pub components: BTreeMap<String, String>,
+  /// Template of components not in `components`; a `div`, or a `span` in
+  /// text, naming the component in `data-component` if not set.
+  pub fallback: Option<String>,
+  /// Template of expressions, where `{value}` is their source.
+  pub expression: String,
+  /// Pass HTML in markdown through, instead of escaping it, and render every
+  /// JSX HTML element and attribute.
+  pub allow_dangerous_html: bool,
+  /// Keep URLs of any protocol, instead of only `http`, `https`, `irc`,
+  /// `ircs`, `mailto` and `xmpp` in links, and `http` and `https` in images.
+  pub allow_dangerous_protocol: bool,
+}
+
+impl Default for HtmlOptions {
+  fn default() -> HtmlOptions {
+    HtmlOptions {
+      components: BTreeMap::new(),
+      fallback: None,
+      expression: "<code class=\"mdx-expression\">{value}</code>".to_owned(),
+      allow_dangerous_html: false,
+      allow_dangerous_protocol: false,
+    }
+  }
+}
+
+const FLOW_FALLBACK: &str = "<div data-component=\"{component}\">{children}</div>";
+const TEXT_FALLBACK: &str = "<span data-component=\"{component}\">{children}</span>";
+
+/// Protocols of URLs kept in links and images, as in `markdown::to_html`.
+const LINK_PROTOCOLS: &[&str] = &["http", "https", "irc", "ircs", "mailto", "xmpp"];
+const IMAGE_PROTOCOLS: &[&str] = &["http", "https"];
+
+/// HTML elements that run code or load other documents; JSX elements with
+/// these names are left out unless dangerous HTML is allowed.
+const DANGEROUS: &[&str] = &["script", "style", "iframe", "object", "embed"];
+
+/// HTML elements without content or a closing tag.
+const VOID: &[&str] = &[
+  "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr",
+];
+
+/// The content of `document` as HTML, with footnotes in a section at the
+/// end.
+pub fn to_html(document: &Document, options: &HtmlOptions) -> String {
+  let mut definitions = HashMap::new();
+  let mut footnotes = HashMap::new();
+  let mut headings = vec![];
+  walk(&document.tree, |node, _| {
+    match node {
+      Node::Definition(d) => {
+        definitions.entry(normalize_identifier(&d.identifier)).or_insert(node);
+      }
+      Node::FootnoteDefinition(d) => {
+        footnotes.entry(normalize_identifier(&d.identifier)).or_insert(node);
+      }
+      Node::Heading(_) => headings.push(node as *const Node),
+      _ => {}
+    }
+    Control::Continue
+  });
+
+  let mut renderer = Renderer {
+    options,
+    definitions,
+    footnotes,
+    ids: headings.into_iter().zip(document.ids.iter().map(String::as_str)).collect(),
+    called: vec![],
+  };
+  let mut html = renderer.block(&document.tree, false);
+  let section = renderer.footnote_section();
+  if !section.is_empty() {
+    if !html.is_empty() {
+      html.push('\n');
+    }
+    html.push_str(&section);
+  }
+  if !html.is_empty() {
+    html.push('\n');
+  }
+  html
+}
+
+struct Renderer<'a> {
+  options: &'a HtmlOptions,
+  /// `Definition`s by normalized identifier.
+  definitions: HashMap<String, &'a Node>,
+  /// `FootnoteDefinition`s by normalized identifier.
+  footnotes: HashMap<String, &'a Node>,
+  /// Ids of headings, which are found by address as footnotes do not
+  /// render in document order.
+  ids: HashMap<*const Node, &'a str>,
+  /// Footnotes referenced so far, in order, with how often.
+  called: Vec<(String, usize)>,
+}
+
+impl<'a> Renderer<'a> {
+  /// A flow node; `tight` leaves paragraphs out, in tight list items.
+  fn block(&mut self, node: &'a Node, tight: bool) -> String {
+    match node {
+      Node::Root(n) => self.flow(&n.children, false),
+      Node::Paragraph(n) if tight => self.phrasing(&n.children),
+      Node::Paragraph(n) => format!("<p>{}</p>", self.phrasing(&n.children)),
+      Node::Heading(n) => {
+        let id = match self.ids.get(&(node as *const Node)) {
+          Some(id) => format!(" id=\"{}\"", escape(id)),
+          None => String::new(),
+        };
+        format!("<h{}{}>{}</h{}>", n.depth, id, self.phrasing(&n.children), n.depth)
+      }
+      Node::ThematicBreak(_) => "<hr />".to_owned(),
+      Node::BlockQuote(n) => wrap("blockquote", "", &self.flow(&n.children, false)),
+      Node::List(n) => self.list(n),
+      Node::Code(n) => {
+        let class = match n.lang.as_deref() {
+          Some(lang) => format!(" class=\"language-{}\"", escape(lang)),
+          None => String::new(),
+        };
+        format!("<pre><code{}>{}</code></pre>", class, escape(&code_value(&n.value)))
+      }
+      Node::Math(n) => format!(
+        "<pre><code class=\"language-math math-display\">{}</code></pre>",
+        escape(&code_value(&n.value))
+      ),
+      Node::Html(n) => self.html(&n.value),
+      Node::Table(n) => self.table(n),
+      Node::MdxFlowExpression(n) => self.expression(&n.value),
+      Node::MdxJsxFlowElement(n) if section::is_section(node) => wrap("section", "", &self.flow(&n.children, false)),
+      Node::MdxJsxFlowElement(n) => self.element(n.name.as_deref(), &n.attributes, &n.children, true),
+      // rendered elsewhere, or not at all
+      Node::Definition(_) | Node::FootnoteDefinition(_) => String::new(),
+      Node::Yaml(_) | Node::Toml(_) | Node::MdxjsEsm(_) => String::new(),
+      _ => self.phrasing(std::slice::from_ref(node)),
+    }
+  }
+
+  fn flow(&mut self, nodes: &'a [Node], tight: bool) -> String {
+    let mut blocks = vec![];
+    for node in nodes {
+      let block = self.block(node, tight);
+      if !block.is_empty() {
+        blocks.push(block);
+      }
+    }
+    blocks.join("\n")
+  }
+
+  fn list(&mut self, list: &'a List) -> String {
+    // one loose item makes the whole list loose
+    let loose = list.spread || list.children.iter().any(|item| matches!(item, Node::ListItem(i) if i.spread));
+    let tasks = list.children.iter().any(|item| matches!(item, Node::ListItem(i) if i.checked.is_some()));
+
+    let mut items = vec![];
+    for item in &list.children {
+      let (mut content, checked) = match item {
+        Node::ListItem(item) => (self.flow(&item.children, !loose), item.checked),
+        other => (self.block(other, !loose), None),
+      };
+      let class = match checked {
+        Some(checked) => {
+          let checkbox = format!(
+            "<input type=\"checkbox\" disabled=\"\"{} /> ",
+            if checked { " checked=\"\"" } else { "" }
+          );
+          let at = if content.starts_with("<p>") { 3 } else { 0 };
+          content.insert_str(at, &checkbox);
+          " class=\"task-list-item\""
+        }
+        None => "",
+      };
+      if loose || content.contains('\n') {
+        items.push(wrap("li", class, &content));
+      } else {
+        items.push(format!("<li{}>{}</li>", class, content));
+      }
+    }
+
+    let class = if tasks { " class=\"contains-task-list\"" } else { "" };
+    match (list.ordered, list.start) {
+      (true, Some(start)) if start != 1 => wrap("ol", &format!(" start=\"{}\"{}", start, class), &items.join("\n")),
+      (true, _) => wrap("ol", class, &items.join("\n")),
+      (false, _) => wrap("ul", class, &items.join("\n")),
+    }
+  }
+
+  fn table(&mut self, table: &'a Table) -> String {
+    let mut rows = vec![];
+    for (index, row) in table.children.iter().enumerate() {
+      let tag = if index == 0 { "th" } else { "td" };
+      let cells = match row {
+        Node::TableRow(row) => &row.children,
+        _ => continue,
+      };
+      let mut html = vec![];
+      for (column, cell) in cells.iter().enumerate() {
+        let align = match table.align.get(column) {
+          Some(AlignKind::Left) => " align=\"left\"",
+          Some(AlignKind::Right) => " align=\"right\"",
+          Some(AlignKind::Center) => " align=\"center\"",
+          _ => "",
+        };
+        let content = match cell {
+          Node::TableCell(cell) => self.phrasing(&cell.children),
+          other => self.phrasing(std::slice::from_ref(other)),
+        };
+        html.push(format!("<{}{}>{}</{}>", tag, align, content, tag));
+      }
+      rows.push(wrap("tr", "", &html.join("\n")));
+    }
+
+    let mut parts = vec![];
+    if !rows.is_empty() {
+      parts.push(wrap("thead", "", &rows.remove(0)));
+    }
+    if !rows.is_empty() {
+      parts.push(wrap("tbody", "", &rows.join("\n")));
+    }
+    wrap("table", "", &parts.join("\n"))
+  }
+
+  fn phrasing(&mut self, nodes: &'a [Node]) -> String {
+    let mut out = String::new();
+    for node in nodes {
+      self.inline(node, &mut out);
+    }
+    out
+  }
+
+  fn inline(&mut self, node: &'a Node, out: &mut String) {
+    match node {
+      Node::Text(n) => out.push_str(&escape(&n.value)),
+      Node::Emphasis(n) => out.push_str(&format!("<em>{}</em>", self.phrasing(&n.children))),
+      Node::Strong(n) => out.push_str(&format!("<strong>{}</strong>", self.phrasing(&n.children))),
+      Node::Delete(n) => out.push_str(&format!("<del>{}</del>", self.phrasing(&n.children))),
+      Node::InlineCode(n) => out.push_str(&format!("<code>{}</code>", escape(&n.value))),
+      Node::InlineMath(n) => out.push_str(&format!(
+        "<code class=\"language-math math-inline\">{}</code>",
+        escape(&n.value)
+      )),
+      Node::Break(_) => out.push_str("<br />\n"),
+      Node::Html(n) => out.push_str(&self.html(&n.value)),
+      Node::Link(n) => {
+        let content = self.phrasing(&n.children);
+        out.push_str(&link(self.url(&n.url, LINK_PROTOCOLS), n.title.as_deref(), &content));
+      }
+      Node::Image(n) => out.push_str(&image(self.url(&n.url, IMAGE_PROTOCOLS), n.title.as_deref(), &n.alt)),
+      Node::LinkReference(n) => {
+        let content = self.phrasing(&n.children);
+        match self.definition(&n.identifier) {
+          Some((url, title)) => out.push_str(&link(self.url(url, LINK_PROTOCOLS), title, &content)),
+          // as written, like an unresolved reference is parsed
+          None => out.push_str(&format!("[{}]", content)),
+        }
+      }
+      Node::ImageReference(n) => match self.definition(&n.identifier) {
+        Some((url, title)) => out.push_str(&image(self.url(url, IMAGE_PROTOCOLS), title, &n.alt)),
+        None => out.push_str(&format!("![{}]", escape(&n.alt))),
+      },
+      Node::FootnoteReference(n) => out.push_str(&self.footnote_reference(&n.identifier, n.label.as_deref())),
+      Node::MdxTextExpression(n) => out.push_str(&self.expression(&n.value)),
+      Node::MdxJsxTextElement(n) => out.push_str(&self.element(n.name.as_deref(), &n.attributes, &n.children, false)),
+      // flow content in phrasing, e.g. from a transform
+      other => out.push_str(&self.block(other, false)),
+    }
+  }
+
+  fn definition(&self, identifier: &str) -> Option<(&'a str, Option<&'a str>)> {
+    match self.definitions.get(&normalize_identifier(identifier)) {
+      Some(Node::Definition(d)) => Some((&d.url, d.title.as_deref())),
+      _ => None,
+    }
+  }
+
+  fn html(&self, value: &str) -> String {
+    if self.options.allow_dangerous_html {
+      value.to_owned()
+    } else {
+      escape(value)
+    }
+  }
+
+  /// `url`, or nothing if its protocol is not in `protocols` and dangerous
+  /// protocols are not allowed.
+  fn url<'u>(&self, url: &'u str, protocols: &[&str]) -> &'u str {
+    if self.options.allow_dangerous_protocol {
+      return url;
+    }
+    match url.find([':', '/', '?', '#']) {
+      Some(end) if url[end..].starts_with(':') && !protocols.contains(&url[..end].to_lowercase().as_str()) => "",
+      _ => url,
+    }
+  }
+
+  fn expression(&self, value: &str) -> String {
+    let trimmed = value.trim();
+    if trimmed.starts_with("/*") && trimmed.ends_with("*/") {
+      return String::new();
+    }
+    fill(&self.options.expression, |name| match name {
+      "value" => Some(escape(value)),
+      _ => None,
+    })
+  }
+
+  /// A JSX element: through its template, as HTML for lowercase names, or
+  /// through the fallback template.
+  fn element(&mut self, name: Option<&str>, attributes: &[AttributeContent], children: &'a [Node], flow: bool) -> String {
+    let content = if flow {
+      self.flow(children, false)
+    } else {
+      self.phrasing(children)
+    };
+    // fragments are only their content
+    let name = match name {
+      Some(name) => name,
+      None => return content,
+    };
+
+    let template = match self.options.components.get(name) {
+      Some(template) => template.as_str(),
+      None if name.starts_with(|c: char| c.is_ascii_lowercase()) && !name.contains('.') => {
+        return self.html_element(name, attributes, &content, flow);
+      }
+      None => match &self.options.fallback {
+        Some(fallback) => fallback.as_str(),
+        None if flow => FLOW_FALLBACK,
+        None => TEXT_FALLBACK,
+      },
+    };
+    // flow content goes on lines of its own
+    let children = if flow && !content.is_empty() {
+      format!("\n{}\n", content)
+    } else {
+      content
+    };
+    fill(template, |key| match key {
+      "children" => Some(children.clone()),
+      "component" => Some(escape(name)),
+      _ => attribute(attributes, key).map(|value| escape(&value)),
+    })
+  }
+
+  /// A JSX element with an HTML name, with its literal attributes; those
+  /// with expressions, and spreads, cannot be evaluated and are left out.
+  fn html_element(&self, name: &str, attributes: &[AttributeContent], content: &str, flow: bool) -> String {
+    let safe = !self.options.allow_dangerous_html;
+    if safe && DANGEROUS.contains(&name) {
+      return String::new();
+    }
+    let mut html = String::new();
+    for attribute in attributes {
+      if let AttributeContent::Property(p) = attribute {
+        let key = match p.name.as_str() {
+          "className" => "class",
+          "htmlFor" => "for",
+          key => key,
+        };
+        // event handlers and styles can run code
+        if safe && (key == "style" || key.to_ascii_lowercase().starts_with("on")) {
+          continue;
+        }
+        match &p.value {
+          Some(AttributeValue::Literal(value)) => {
+            let value = match key {
+              "href" => self.url(value, LINK_PROTOCOLS),
+              "src" => self.url(value, IMAGE_PROTOCOLS),
+              _ => value,
+            };
+            html.push_str(&format!(" {}=\"{}\"", key, escape(value)));
+          }
+          Some(AttributeValue::Expression(..)) => {}
+          None => html.push_str(&format!(" {}=\"\"", key)),
+        }
+      }
+    }
+    if VOID.contains(&name) {
+      format!("<{}{} />", name, html)
+    } else if flow {
+      wrap(name, &html, content)
+    } else {
+      format!("<{}{}>{}</{}>", name, html, content, name)
+    }
+  }
+
+  /// A reference to a footnote, numbered in the order footnotes are first
+  /// referenced in.
+  fn footnote_reference(&mut self, identifier: &str, label: Option<&str>) -> String {
+    let key = normalize_identifier(identifier);
+    if !self.footnotes.contains_key(&key) {
+      return format!("[^{}]", escape(label.unwrap_or(identifier)));
+    }
+    let number = match self.called.iter().position(|(k, _)| *k == key) {
+      Some(index) => {
+        self.called[index].1 += 1;
+        index + 1
+      }
+      None => {
+        self.called.push((key.clone(), 1));
+        self.called.len()
+      }
+    };
+    let count = self.called[number - 1].1;
+    format!(
+      "<sup><a href=\"#user-content-fn-{}\" id=\"{}\" data-footnote-ref=\"\" aria-describedby=\"footnote-label\">{}</a></sup>",
+      number,
+      reference_id(number, count),
+      number
+    )
+  }
+
+  /// The referenced footnotes, each with links back to its references.
+  fn footnote_section(&mut self) -> String {
+    let mut items = vec![];
+    // footnotes can reference footnotes, which are added as they go
+    let mut index = 0;
+    while index < self.called.len() {
+      let number = index + 1;
+      let children = match self.footnotes.get(&self.called[index].0) {
+        Some(Node::FootnoteDefinition(d)) => &d.children,
+        _ => unreachable!("only defined footnotes are called"),
+      };
+      let mut content = self.flow(children, false);
+      let backrefs = (1..=self.called[index].1)
+        .map(|count| {
+          format!(
+            "<a href=\"#{}\" data-footnote-backref=\"\" aria-label=\"Back to reference {}\" class=\"data-footnote-backref\">↩{}</a>",
+            reference_id(number, count),
+            number,
+            if count > 1 { format!("<sup>{}</sup>", count) } else { String::new() }
+          )
+        })
+        .collect::<Vec<String>>()
+        .join(" ");
+      if content.ends_with("</p>") {
+        content.insert_str(content.len() - 4, &format!(" {}", backrefs));
+      } else {
+        if !content.is_empty() {
+          content.push('\n');
+        }
+        content.push_str(&backrefs);
+      }
+      items.push(wrap("li", &format!(" id=\"user-content-fn-{}\"", number), &content));
+      index += 1;
+    }
+
+    if items.is_empty() {
+      return String::new();
+    }
+    format!(
+      "<section data-footnotes=\"\" class=\"footnotes\"><h2 id=\"footnote-label\" class=\"sr-only\">Footnotes</h2>\n{}\n</section>",
+      wrap("ol", "", &items.join("\n"))
+    )
+  }
+}
+
+/// The id of the `count`th reference to footnote `number`.
+fn reference_id(number: usize, count: usize) -> String {
+  if count > 1 {
+    format!("user-content-fnref-{}-{}", number, count)
+  } else {
+    format!("user-content-fnref-{}", number)
+  }
+}
+
+/// `<tag attributes>` and `</tag>` on lines of their own around `content`.
+fn wrap(tag: &str, attributes: &str, content: &str) -> String {
+  if content.is_empty() {
+    format!("<{}{}>\n</{}>", tag, attributes, tag)
+  } else {
+    format!("<{}{}>\n{}\n</{}>", tag, attributes, content, tag)
+  }
+}
+
+/// Code ends in a line ending, as `markdown::to_html` writes it.
+fn code_value(value: &str) -> String {
+  if value.is_empty() {
+    String::new()
+  } else {
+    format!("{}\n", value)
+  }
+}
+
+fn link(url: &str, title: Option<&str>, content: &str) -> String {
+  let title = title.map(|t| format!(" title=\"{}\"", escape(t))).unwrap_or_default();
+  format!("<a href=\"{}\"{}>{}</a>", escape(url), title, content)
+}
+
+fn image(url: &str, title: Option<&str>, alt: &str) -> String {
+  let title = title.map(|t| format!(" title=\"{}\"", escape(t))).unwrap_or_default();
+  format!("<img src=\"{}\" alt=\"{}\"{} />", escape(url), escape(alt), title)
+}
+
+/// The value of the attribute `name`: its text, the source of an
+/// expression, or `true` when it has no value.
+fn attribute(attributes: &[AttributeContent], name: &str) -> Option<String> {
+  attributes.iter().rev().find_map(|attribute| match attribute {
+    AttributeContent::Property(p) if p.name == name => Some(match &p.value {
+      Some(AttributeValue::Literal(value)) => value.clone(),
+      Some(AttributeValue::Expression(value, _)) => value.clone(),
+      None => "true".to_owned(),
+    }),
+    _ => None,
+  })
+}
+
+/// `template` with every `{name}` replaced by `value(name)`, or nothing if
+/// it has no value. `{{` and `}}` are literal braces.
+fn fill(template: &str, value: impl Fn(&str) -> Option<String>) -> String {
+  let mut out = String::new();
+  let mut rest = template;
+  while let Some(open) = rest.find(['{', '}']) {
+    out.push_str(&rest[..open]);
+    let after = &rest[open + 1..];
+    if rest[open..].starts_with("{{") || rest[open..].starts_with("}}") {
+      out.push_str(&rest[open..open + 1]);
+      rest = &after[1..];
+      continue;
+    }
+    let end = after
+      .find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+      .unwrap_or(after.len());
+    if rest[open..].starts_with('{') && end > 0 && after[end..].starts_with('}') {
+      out.push_str(&value(&after[..end]).unwrap_or_default());
+      rest = &after[end + 1..];
+    } else {
+      out.push_str(&rest[open..open + 1]);
+      rest = after;
+    }
+  }
+  out.push_str(rest);
+  out
+}
+
+fn escape(value: &str) -> String {
+  value
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::Syntax;
+  use crate::{parse_str, Options};
+
+  fn render(source: &str, syntax: Syntax, options: &HtmlOptions) -> String {
+    let options_ = Options {
+      parse: syntax.parse_options(),
+      ..Options::default()
+    };
+    to_html(&parse_str(source, &options_).unwrap(), options)
+  }
+
+  #[test]
+  fn renders_markdown() {
+    let syntax = Syntax {
+      gfm: true,
+      math: true,
+      ..Syntax::default()
+    };
+    let source = "# Hi *there* {#top}\n\n- [x] done\n- b\n\n| a | b |\n| :- | -: |\n| 1 | 2 |\n\n\
+      See [it][ref], $x^2$ and[^n] again[^n].\n\n$$\ny\n$$\n\n[ref]: /it \"T\"\n\n[^n]: A & B.\n";
+    assert_eq!(
+      render(source, syntax, &HtmlOptions::default()),
+      "<h1 id=\"top\">Hi <em>there</em></h1>
+<ul class=\"contains-task-list\">
+<li class=\"task-list-item\"><input type=\"checkbox\" disabled=\"\" checked=\"\" /> done</li>
+<li>b</li>
+</ul>
+<table>
+<thead>
+<tr>
+<th align=\"left\">a</th>
+<th align=\"right\">b</th>
+</tr>
+</thead>
+<tbody>
+<tr>
+<td align=\"left\">1</td>
+<td align=\"right\">2</td>
+</tr>
+</tbody>
+</table>
+<p>See <a href=\"/it\" title=\"T\">it</a>, <code class=\"language-math math-inline\">x^2</code> and\
+<sup><a href=\"#user-content-fn-1\" id=\"user-content-fnref-1\" data-footnote-ref=\"\" aria-describedby=\"footnote-label\">1</a></sup> again\
+<sup><a href=\"#user-content-fn-1\" id=\"user-content-fnref-1-2\" data-footnote-ref=\"\" aria-describedby=\"footnote-label\">1</a></sup>.</p>
+<pre><code class=\"language-math math-display\">y
+</code></pre>
+<section data-footnotes=\"\" class=\"footnotes\"><h2 id=\"footnote-label\" class=\"sr-only\">Footnotes</h2>
+<ol>
+<li id=\"user-content-fn-1\">
+<p>A &amp; B. \
+<a href=\"#user-content-fnref-1\" data-footnote-backref=\"\" aria-label=\"Back to reference 1\" class=\"data-footnote-backref\">↩</a> \
+<a href=\"#user-content-fnref-1-2\" data-footnote-backref=\"\" aria-label=\"Back to reference 1\" class=\"data-footnote-backref\">↩<sup>2</sup></a></p>
+</li>
+</ol>
+</section>
+"
+    );
+  }
+
+  #[test]
+  fn escapes_html_and_unsafe_urls() {
+    let source = "<b>bold</b> [a](javascript:alert(1)) [b](MAILTO:a@b.c) ![c](data:image/png;base64,x) [d](/x:y)\n\n\
+      <script>x</script>\n";
+    assert_eq!(
+      render(source, Syntax::default(), &HtmlOptions::default()),
+      "<p>&lt;b&gt;bold&lt;/b&gt; <a href=\"\">a</a> <a href=\"MAILTO:a@b.c\">b</a> <img src=\"\" alt=\"c\" /> \
+       <a href=\"/x:y\">d</a></p>\n&lt;script&gt;x&lt;/script&gt;\n"
+    );
+
+    let dangerous = HtmlOptions {
+      allow_dangerous_html: true,
+      allow_dangerous_protocol: true,
+      ..HtmlOptions::default()
+    };
+    assert_eq!(
+      render(source, Syntax::default(), &dangerous),
+      "<p><b>bold</b> <a href=\"javascript:alert(1)\">a</a> <a href=\"MAILTO:a@b.c\">b</a> \
+       <img src=\"data:image/png;base64,x\" alt=\"c\" /> <a href=\"/x:y\">d</a></p>\n<script>x</script>\n"
+    );
+
+    let mdx = Syntax {
+      mdx: true,
+      ..Syntax::default()
+    };
+    assert_eq!(
+      render("<a href=\"javascript:x\">a</a>\n", mdx, &HtmlOptions::default()),
+      "<p><a href=\"\">a</a></p>\n"
+    );
+
+    let jsx = "<div onClick=\"alert(1)\" style=\"color: red\" title=\"t\">\n  <script>\n    alert(1)\n  </script>\n</div>\n";
+    assert_eq!(
+      render(jsx, mdx, &HtmlOptions::default()),
+      "<div title=\"t\">\n</div>\n"
+    );
+    assert_eq!(
+      render(jsx, mdx, &dangerous),
+      "<div onClick=\"alert(1)\" style=\"color: red\" title=\"t\">\n<script>\n<p>alert(1)</p>\n</script>\n</div>\n"
+    );
+  }
+
+  #[test]
+  fn maps_components() {
+    let syntax = Syntax {
+      mdx: true,
+      ..Syntax::default()
+    };
+    let source = "<Note type=\"warning\" open>\n  Some <Badge>new</Badge> {props.name}.\n</Note>\n\n\
+      <div className=\"box\" style={{a: 1}}><img src=\"a.png\" /></div>\n\n<>\n  <Tabs.Item />\n</>\n\n{/* comment */}\n";
+    let options: HtmlOptions = serde_json::from_str(
+      r#"{ "components": { "Note": "<aside class=\"note-{type}\" data-open=\"{open}\">{children}</aside>" } }"#,
+    )
+    .unwrap();
+    assert_eq!(
+      render(source, syntax, &options),
+      "<aside class=\"note-warning\" data-open=\"true\">
+<p>Some <span data-component=\"Badge\">new</span> <code class=\"mdx-expression\">props.name</code>.</p>
+</aside>
+<div class=\"box\">
+<img src=\"a.png\" />
+</div>
+<div data-component=\"Tabs.Item\"></div>
+"
+    );
+  }
+}
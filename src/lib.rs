@@ -0,0 +1,325 @@
+//! Parse markdown/MDX documents into `markdown::mdast` trees and serialize
+//! them, along with their frontmatter, to JSON.
+//!
+//! ```no_run
+//! use markdown_rs_test::{parse_file, Options, SerializeOptions};
+//!
+//! let document = parse_file("src/input.mdx".as_ref(), &Options::default())?;
+//! println!("{}", document.to_string(&SerializeOptions::default()));
+//! # Ok::<(), String>(())
+//! ```
+
+pub mod assets;
+pub mod chunk;
+pub mod code;
+pub mod config;
+pub mod diagnostic;
+pub mod directives;
+pub mod format;
+pub mod frontmatter;
+pub mod html;
+pub mod links;
+pub mod lint;
+mod position;
+pub mod resolve;
+pub mod schema;
+pub mod section;
+pub mod select;
+pub mod slug;
+pub mod source;
+pub mod stats;
+pub mod stringify;
+pub mod tangle;
+#[cfg(test)]
+mod testing;
+pub mod text;
+pub mod toc;
+pub mod transform;
+pub mod visit;
+pub mod wrappers;
+
+use assets::Asset;
+use diagnostic::Diagnostic;
+use directives::Directives;
+use frontmatter::Frontmatter;
+use resolve::Resolution;
+use schema::Schema;
+use source::Source;
+use stats::Stats;
+use toc::{Entry, TocOptions};
+use transform::Pipeline;
+use wrappers::MyNode;
+
+use markdown::mdast::Node;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How documents are parsed.
+#[derive(Default)]
+pub struct Options {
+  /// Constructs `markdown::to_mdast` recognizes; CommonMark by default.
+  pub parse: markdown::ParseOptions,
+  /// Schema the frontmatter is validated against.
+  pub schema: Option<Schema>,
+  /// Transforms run over the tree once it is parsed.
+  pub transforms: Pipeline,
+  /// Collect the images and other assets of documents read from files, and
+  /// check that they exist.
+  pub assets: bool,
+  /// Directory that paths starting with `/` are relative to.
+  pub root: Option<PathBuf>,
+  /// Check that JSON, YAML, TOML and XML code blocks parse.
+  pub check_code: bool,
+}
+
+/// How a `Document` is turned into JSON.
+#[derive(Debug, Clone, Default)]
+pub struct SerializeOptions {
+  pub pretty: bool,
+  /// Include the encoding and line ending normalizations applied to the file.
+  pub normalizations: bool,
+  /// Include the table of contents of the document.
+  pub toc: Option<TocOptions>,
+  /// Include the statistics of the document.
+  pub stats: bool,
+}
+
+/// A parsed document.
+///
+/// Every position, in the tree, the frontmatter and the diagnostics, is
+/// relative to the start of the file, and offsets are bytes on disk.
+#[derive(Debug)]
+pub struct Document {
+  pub path: Option<PathBuf>,
+  pub source: Source,
+  pub frontmatter: Option<Frontmatter>,
+  pub tree: Node,
+  pub diagnostics: Vec<Diagnostic>,
+  /// Anchor ids of the headings in `tree`, in document order.
+  pub ids: Vec<String>,
+  /// What each reference in `tree` points to, in document order; `None` for
+  /// references whose definition a transform removed.
+  pub references: Vec<Option<Resolution>>,
+  /// Lint directives and whether the document is marked as generated, read
+  /// from the tree before transforms ran.
+  pub directives: Directives,
+  /// Assets used by the document, if asked for in `Options::assets`.
+  pub assets: Option<Vec<Asset>>,
+}
+
+pub fn parse_file(path: &Path, options: &Options) -> Result<Document, String> {
+  let source = Source::read(path)?;
+  let mut document = parse_source(source, options).map_err(|e| format!("{}:{}", path.display(), e))?;
+  if options.assets {
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    let found = assets::assets(
+      &document.tree,
+      &document.references,
+      base,
+      options.root.as_deref(),
+      &mut document.diagnostics,
+    );
+    document.assets = Some(found);
+  }
+  document.path = Some(path.to_owned());
+  Ok(document)
+}
+
+pub fn parse_str(value: &str, options: &Options) -> Result<Document, String> {
+  parse_source(Source::decode(value.as_bytes())?, options)
+}
+
+fn parse_source(source: Source, options: &Options) -> Result<Document, String> {
+  // split contents into YAML, TOML or JSON frontmatter and actual content;
+  // frontmatter that does not parse is reported, not fatal
+  let split = frontmatter::split_lenient(source.text.as_str());
+  let mut frontmatter = split.frontmatter;
+
+  let mut diagnostics = vec![];
+  match (split.error, &options.schema) {
+    (Some(error), _) => diagnostics.push(error),
+    (None, Some(schema)) => diagnostics.extend(schema.validate(frontmatter.as_ref())),
+    (None, None) => {}
+  }
+
+  let mut tree = markdown::to_mdast(split.content, &options.parse)?;
+  // positions should point into the file, not into the content after the frontmatter
+  position::shift(&mut tree, &split.content_start);
+
+  // and offsets at the bytes on disk, not at the normalized text
+  position::remap(&mut tree, &source);
+  if let Some(frontmatter) = &mut frontmatter {
+    source.remap_position(&mut frontmatter.position);
+  }
+  for diagnostic in &mut diagnostics {
+    if let Some(position) = &mut diagnostic.position {
+      source.remap_position(position);
+    }
+  }
+
+  if options.check_code {
+    diagnostics.extend(code::check(&tree, &source));
+  }
+
+  // before transforms, which may remove the comments they are in
+  let directives = Directives::of(&tree, &source.text);
+
+  // transforms see, and report, positions in the file
+  options.transforms.run(&mut tree, &mut diagnostics);
+  let ids = slug::heading_ids(&mut tree);
+  let references = resolve::resolve(&tree, &source, &options.parse.constructs, &mut diagnostics);
+
+  Ok(Document {
+    path: None,
+    source,
+    frontmatter,
+    tree,
+    diagnostics,
+    ids,
+    references,
+    directives,
+    assets: None,
+  })
+}
+
+impl Document {
+  pub fn to_json(&self, options: &SerializeOptions) -> Value {
+    let node: MyNode = self.tree.clone().into();
+    let mut tree = json!(node);
+    add_ids(&mut tree, &mut self.ids.iter());
+    add_resolutions(&mut tree, &mut self.references.iter());
+    if let Some(assets) = &self.assets {
+      add_dimensions(&mut tree, assets);
+    }
+    let mut obj = json!({
+      "file": self.path,
+      "frontmatter": self.frontmatter,
+      "tree": tree,
+      "diagnostics": self.diagnostics,
+    });
+    if options.normalizations {
+      obj["normalizations"] = json!(self.source.normalizations);
+    }
+    if let Some(assets) = &self.assets {
+      obj["assets"] = json!(assets);
+    }
+    if options.stats {
+      obj["stats"] = json!(self.stats());
+    }
+    if let Some(toc) = &options.toc {
+      obj["toc"] = json!(self.toc(toc));
+    }
+    obj
+  }
+
+  pub fn stats(&self) -> Stats {
+    Stats::of(&self.tree)
+  }
+
+  pub fn toc(&self, options: &TocOptions) -> Vec<Entry> {
+    toc::toc(&self.tree, &self.ids, options)
+  }
+
+  pub fn to_string(&self, options: &SerializeOptions) -> String {
+    let obj = self.to_json(options);
+    if options.pretty {
+      serde_json::to_string_pretty(&obj).unwrap()
+    } else {
+      serde_json::to_string(&obj).unwrap()
+    }
+  }
+}
+
+/// Add `id`s to the serialized headings in `node`, in document order.
+fn add_ids<'a>(node: &mut Value, ids: &mut impl Iterator<Item = &'a String>) {
+  if node["type"] == "Heading" {
+    if let Some(id) = ids.next() {
+      node["id"] = json!(id);
+    }
+  }
+  if let Some(Value::Array(children)) = node.get_mut("children") {
+    for child in children {
+      add_ids(child, ids);
+    }
+  }
+  // a section has the id of its heading
+  if node["type"] == "Section" {
+    node["id"] = node["children"][0]["id"].clone();
+  }
+}
+
+/// Add what the serialized references in `node` point to, in document order.
+fn add_resolutions<'a>(node: &mut Value, resolutions: &mut impl Iterator<Item = &'a Option<Resolution>>) {
+  if matches!(node["type"].as_str(), Some("LinkReference" | "ImageReference" | "FootnoteReference")) {
+    match resolutions.next() {
+      Some(Some(Resolution::Definition { url, title })) => {
+        node["url"] = json!(url);
+        node["title"] = json!(title);
+      }
+      Some(Some(Resolution::Footnote { index })) => node["index"] = json!(index),
+      _ => {}
+    }
+  }
+  if let Some(Value::Array(children)) = node.get_mut("children") {
+    for child in children {
+      add_resolutions(child, resolutions);
+    }
+  }
+}
+
+/// Add the `width` and `height` of the files serialized images point to.
+fn add_dimensions(node: &mut Value, assets: &[Asset]) {
+  let mut sizes = HashMap::new();
+  for asset in assets {
+    if let (Some(position), Some(width), Some(height)) = (&asset.position, asset.width, asset.height) {
+      sizes.insert(position.start.offset, (width, height));
+    }
+  }
+
+  fn add(node: &mut Value, sizes: &HashMap<usize, (u32, u32)>) {
+    if matches!(node["type"].as_str(), Some("Image" | "ImageReference")) {
+      let offset = node["position"]["start"]["offset"].as_u64();
+      if let Some((width, height)) = offset.and_then(|o| sizes.get(&(o as usize))) {
+        node["width"] = json!(width);
+        node["height"] = json!(height);
+      }
+    }
+    if let Some(Value::Array(children)) = node.get_mut("children") {
+      for child in children {
+        add(child, sizes);
+      }
+    }
+  }
+  add(node, &sizes);
+}
+
+/// Expand directories into the markdown files they contain, in a stable order.
+pub fn collect_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+  fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    let mut entries = fs::read_dir(dir)
+      .map_err(|e| format!("{}: {}", dir.display(), e))?
+      .filter_map(|entry| entry.ok().map(|e| e.path()))
+      .collect::<Vec<PathBuf>>();
+    entries.sort();
+    for path in entries {
+      if path.is_dir() {
+        walk(&path, files)?;
+      } else if matches!(path.extension().and_then(|e| e.to_str()), Some("md" | "mdx")) {
+        files.push(path);
+      }
+    }
+    Ok(())
+  }
+
+  let mut files = vec![];
+  for path in paths {
+    if path.is_dir() {
+      walk(path, &mut files)?;
+    } else {
+      files.push(path.clone());
+    }
+  }
+  Ok(files)
+}
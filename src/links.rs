@@ -0,0 +1,316 @@
+//! Find the links in documents and check them, without the network.
+//!
+//! Relative links must point at files that exist and, when they have a
+//! `#fragment`, at a heading of the target document. External URLs are
+//! only listed.
+
+use crate::diagnostic::Diagnostic;
+use crate::visit::{walk, Control};
+use crate::wrappers::MyPosition;
+use crate::Document;
+use markdown::mdast::{AttributeContent, AttributeValue, Node};
+use markdown::unist::Position;
+
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+  /// A URL with a scheme, e.g. `https://`, or protocol-relative (`//`).
+  External,
+  /// A path to a file, maybe with a `#fragment`.
+  Relative,
+  /// Only a `#fragment`, in the same document.
+  Anchor,
+  Mailto,
+}
+
+pub fn classify(url: &str) -> Kind {
+  if matches!(url.get(..7), Some(scheme) if scheme.eq_ignore_ascii_case("mailto:")) {
+    return Kind::Mailto;
+  }
+  if url.starts_with('#') {
+    return Kind::Anchor;
+  }
+  let scheme = url
+    .find(':')
+    .map(|colon| &url[..colon])
+    .filter(|s| s.starts_with(|c: char| c.is_ascii_alphabetic()))
+    .filter(|s| s.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')));
+  if scheme.is_some() || url.starts_with("//") {
+    Kind::External
+  } else {
+    Kind::Relative
+  }
+}
+
+/// A link found in a document.
+#[derive(Debug, Clone)]
+pub struct Link {
+  pub url: String,
+  pub kind: Kind,
+  /// Where the link comes from: a node type, or `Name.href` / `Name.src`
+  /// for JSX attributes.
+  pub from: String,
+  /// Of the node, or of the JSX element for attributes.
+  pub position: Option<Position>,
+}
+
+impl Serialize for Link {
+  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    let mut state = serializer.serialize_struct("Link", 4)?;
+    state.serialize_field("url", &self.url)?;
+    state.serialize_field("kind", &self.kind)?;
+    state.serialize_field("from", &self.from)?;
+    if let Some(p) = self.position.clone() {
+      let pos: MyPosition = MyPosition::from(p);
+      state.serialize_field("position", &pos)?;
+    }
+    state.end()
+  }
+}
+
+/// Links of `Link`, `Definition` and `Image` nodes, and `href` and `src`
+/// attributes of JSX elements, in document order.
+pub fn links(tree: &Node) -> Vec<Link> {
+  let mut links = vec![];
+  let mut add = |url: &str, from: String, position: &Option<Position>| {
+    links.push(Link {
+      url: url.to_owned(),
+      kind: classify(url),
+      from,
+      position: position.clone(),
+    })
+  };
+
+  walk(tree, |node, _| {
+    let (name, attributes, position) = match node {
+      Node::Link(n) => {
+        add(&n.url, "Link".to_owned(), &n.position);
+        return Control::Continue;
+      }
+      Node::Definition(n) => {
+        add(&n.url, "Definition".to_owned(), &n.position);
+        return Control::Continue;
+      }
+      Node::Image(n) => {
+        add(&n.url, "Image".to_owned(), &n.position);
+        return Control::Continue;
+      }
+      Node::MdxJsxFlowElement(n) => (&n.name, &n.attributes, &n.position),
+      Node::MdxJsxTextElement(n) => (&n.name, &n.attributes, &n.position),
+      _ => return Control::Continue,
+    };
+    for attribute in attributes {
+      if let AttributeContent::Property(p) = attribute {
+        if let ("href" | "src", Some(AttributeValue::Literal(url))) = (p.name.as_str(), &p.value) {
+          let from = format!("{}.{}", name.as_deref().unwrap_or("Fragment"), p.name);
+          add(url, from, position);
+        }
+      }
+    }
+    Control::Continue
+  });
+  links
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct LinkOptions {
+  /// Directory that links starting with `/` are relative to; such links are
+  /// not checked without it.
+  pub root: Option<PathBuf>,
+}
+
+/// Checks links against the files on disk and the headings of a corpus of
+/// documents.
+pub struct Checker<'a> {
+  /// Heading ids of the documents, by canonical path.
+  anchors: HashMap<PathBuf, &'a [String]>,
+  options: &'a LinkOptions,
+}
+
+impl<'a> Checker<'a> {
+  pub fn new(documents: &'a [Document], options: &'a LinkOptions) -> Checker<'a> {
+    let anchors = documents
+      .iter()
+      .filter_map(|d| {
+        let path = fs::canonicalize(d.path.as_ref()?).ok()?;
+        Some((path, d.ids.as_slice()))
+      })
+      .collect();
+    Checker { anchors, options }
+  }
+
+  /// Problems with the links of `document`.
+  pub fn check(&self, document: &Document) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let base = document
+      .path
+      .as_deref()
+      .and_then(Path::parent)
+      .unwrap_or_else(|| Path::new(""));
+
+    for link in links(&document.tree) {
+      let message = match link.kind {
+        // `#` alone is the top of the page
+        Kind::Anchor if link.url == "#" => None,
+        Kind::Anchor => missing_anchor(&document.ids, &link.url[1..], "this document"),
+        Kind::Relative => self.check_relative(base, &link.url),
+        Kind::External | Kind::Mailto => None,
+      };
+      if let Some(message) = message {
+        diagnostics.push(Diagnostic::error("links", message, link.position.clone()));
+      }
+    }
+    diagnostics
+  }
+
+  fn check_relative(&self, base: &Path, url: &str) -> Option<String> {
+    let fragment = url.split_once('#').map(|(_, fragment)| fragment);
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let full = local_path(url, base, self.options.root.as_deref())?;
+    let target = match candidates(&full, path.ends_with('/')).into_iter().find(|c| c.is_file()) {
+      Some(target) => target,
+      None if full.is_dir() && fragment.is_none() => return None,
+      None => return Some(format!("`{}` not found", url)),
+    };
+
+    let fragment = fragment.filter(|f| !f.is_empty())?;
+    // anchors can only be checked in documents of the corpus
+    let ids = self.anchors.get(&fs::canonicalize(&target).ok()?)?;
+    missing_anchor(ids, fragment, &percent_decode(path))
+  }
+}
+
+/// Where the relative `url` points on disk: its path, without query and
+/// fragment and percent-decoded, resolved against `base`, or against `root`
+/// when it starts with `/`. `None` for other urls, and for urls starting
+/// with `/` without a `root`.
+pub(crate) fn local_path(url: &str, base: &Path, root: Option<&Path>) -> Option<PathBuf> {
+  if classify(url) != Kind::Relative {
+    return None;
+  }
+  let path = percent_decode(url.split(['?', '#']).next().unwrap_or_default());
+  match path.strip_prefix('/') {
+    Some(rest) => Some(root?.join(rest)),
+    None => Some(base.join(&path)),
+  }
+}
+
+fn missing_anchor(ids: &[String], fragment: &str, target: &str) -> Option<String> {
+  let fragment = percent_decode(fragment);
+  if ids.contains(&fragment) {
+    None
+  } else {
+    Some(format!("no heading `#{}` in {}", fragment, target))
+  }
+}
+
+/// Files a link to `path` may mean: the file itself, or, for site-style
+/// links without an extension, a markdown file or index.
+fn candidates(path: &Path, directory: bool) -> Vec<PathBuf> {
+  let mut candidates = vec![];
+  if !directory {
+    candidates.push(path.to_owned());
+    if path.extension().is_none() {
+      candidates.push(path.with_extension("md"));
+      candidates.push(path.with_extension("mdx"));
+    }
+  }
+  candidates.push(path.join("index.md"));
+  candidates.push(path.join("index.mdx"));
+  candidates
+}
+
+fn percent_decode(value: &str) -> String {
+  let bytes = value.as_bytes();
+  let mut decoded = Vec::with_capacity(bytes.len());
+  let mut index = 0;
+  while index < bytes.len() {
+    let hex = bytes
+      .get(index + 1..index + 3)
+      .and_then(|h| std::str::from_utf8(h).ok())
+      .and_then(|h| u8::from_str_radix(h, 16).ok());
+    match (bytes[index], hex) {
+      (b'%', Some(byte)) => {
+        decoded.push(byte);
+        index += 3;
+      }
+      (byte, _) => {
+        decoded.push(byte);
+        index += 1;
+      }
+    }
+  }
+  String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::TempDir;
+  use crate::{parse_file, Options};
+
+  #[test]
+  fn classifies_urls() {
+    assert_eq!(classify("https://example.com"), Kind::External);
+    assert_eq!(classify("//cdn.example.com/a.js"), Kind::External);
+    assert_eq!(classify("MAILTO:a@b.c"), Kind::Mailto);
+    assert_eq!(classify("#usage"), Kind::Anchor);
+    assert_eq!(classify("../a.md#usage"), Kind::Relative);
+    assert_eq!(classify("/docs/a"), Kind::Relative);
+    assert_eq!(classify("a:b/c.md"), Kind::External);
+  }
+
+  #[test]
+  fn checks_files_and_anchors() {
+    let dir = TempDir::new("links");
+    dir.write("guide/install.md", "# Install\n\n## On Linux\n");
+    dir.write(
+      "index.mdx",
+      "# Home\n\n[a](guide/install.md#on-linux) [b](guide/install#on-mac) [c](missing.md)\n\n\
+       [d](#home) [e](#nope) [f](https://example.com/x#y) [g](#)\n\n<Card href=\"/guide/install\" />\n",
+    );
+
+    let options = Options {
+      parse: markdown::ParseOptions {
+        constructs: markdown::Constructs::mdx(),
+        ..markdown::ParseOptions::default()
+      },
+      ..Options::default()
+    };
+    let documents = ["index.mdx", "guide/install.md"]
+      .iter()
+      .map(|f| parse_file(&dir.path().join(f), &options).unwrap())
+      .collect::<Vec<Document>>();
+
+    let found = links(&documents[0].tree);
+    let froms = found.iter().map(|l| l.from.as_str()).collect::<Vec<&str>>();
+    assert_eq!(froms, vec!["Link", "Link", "Link", "Link", "Link", "Link", "Link", "Card.href"]);
+
+    let link_options = LinkOptions {
+      root: Some(dir.path().to_owned()),
+    };
+    let checker = Checker::new(&documents, &link_options);
+    let messages = checker
+      .check(&documents[0])
+      .into_iter()
+      .map(|d| d.message)
+      .collect::<Vec<String>>();
+    assert_eq!(
+      messages,
+      vec![
+        "no heading `#on-mac` in guide/install",
+        "`missing.md` not found",
+        "no heading `#nope` in this document",
+      ]
+    );
+  }
+}
@@ -0,0 +1,756 @@
+//! Check documents against conventions, with configurable rules.
+//!
+//! Every built-in rule is on, as a warning, unless the config file says
+//! otherwise; `overrides` change rules for the files matching a pattern:
+//!
+//! ```json
+//! {
+//!   "lint": {
+//!     "rules": { "single-h1": "error", "no-bare-urls": "off" },
+//!     "overrides": [{ "files": ["docs/generated/*"], "rules": { "heading-increment": "off" } }]
+//!   }
+//! }
+//! ```
+//!
+//! Rules can attach edits that fix a problem; `apply_fixes` makes them in the
+//! file, for `lint --fix`.
+
+use crate::diagnostic::{Diagnostic, Edit, Severity};
+use crate::slug;
+use crate::tangle::glob;
+use crate::visit::{walk, Control};
+use crate::Document;
+use markdown::mdast::Node;
+use markdown::unist::{Point, Position};
+
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// A problem found by a rule.
+#[derive(Debug, Clone)]
+pub struct Problem {
+  pub message: String,
+  pub position: Option<Position>,
+  /// Edits that fix it, in order and not overlapping; empty if it cannot be
+  /// fixed automatically.
+  pub fix: Vec<Edit>,
+}
+
+impl Problem {
+  pub fn new(message: String, position: Option<Position>) -> Problem {
+    Problem {
+      message,
+      position,
+      fix: vec![],
+    }
+  }
+
+  pub fn with_fix(mut self, fix: Vec<Edit>) -> Problem {
+    self.fix = fix;
+    self
+  }
+}
+
+pub trait Rule {
+  /// The name of the rule in the config file and in diagnostics.
+  fn name(&self) -> &str;
+
+  /// Problems with `document`.
+  fn check(&self, document: &Document) -> Vec<Problem>;
+}
+
+/// How a rule is reported.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+  /// The rule does not run.
+  Off,
+  #[default]
+  Warning,
+  Error,
+}
+
+impl Level {
+  fn severity(self) -> Option<Severity> {
+    match self {
+      Level::Off => None,
+      Level::Warning => Some(Severity::Warning),
+      Level::Error => Some(Severity::Error),
+    }
+  }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct LintOptions {
+  /// Level of each rule by name; rules not listed are warnings.
+  pub rules: BTreeMap<String, Level>,
+  /// Levels for some files, applied in order after `rules`.
+  pub overrides: Vec<Override>,
+  /// What to do with documents marked as generated; see `directives`.
+  pub generated: Generated,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Generated {
+  /// Do not lint them.
+  #[default]
+  Skip,
+  /// Report errors as warnings.
+  Relax,
+  /// Lint them like any other document.
+  Check,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Override {
+  /// Patterns of paths, as given on the command line, with `*` and `?`
+  /// wildcards.
+  pub files: Vec<String>,
+  pub rules: BTreeMap<String, Level>,
+}
+
+/// Rules to run, and how to report them.
+#[derive(Default)]
+pub struct Linter {
+  rules: Vec<Box<dyn Rule>>,
+  options: LintOptions,
+}
+
+impl Linter {
+  /// A linter without rules.
+  pub fn new(options: LintOptions) -> Linter {
+    Linter {
+      rules: vec![],
+      options,
+    }
+  }
+
+  /// A linter with every built-in rule.
+  pub fn with_builtins(options: LintOptions) -> Result<Linter, String> {
+    let mut linter = Linter::new(options);
+    for name in BUILTINS {
+      linter.push(builtin(name).unwrap());
+    }
+    linter.check_names()?;
+    Ok(linter)
+  }
+
+  pub fn push(&mut self, rule: Box<dyn Rule>) {
+    self.rules.push(rule);
+  }
+
+  /// Make sure every rule named in the options is known.
+  pub fn check_names(&self) -> Result<(), String> {
+    let configured = self
+      .options
+      .rules
+      .keys()
+      .chain(self.options.overrides.iter().flat_map(|o| o.rules.keys()));
+    for name in configured {
+      if !self.rules.iter().any(|r| r.name() == name) {
+        let known = self.rules.iter().map(|r| r.name()).collect::<Vec<&str>>();
+        return Err(format!("unknown lint rule `{}`, expected one of: {}", name, known.join(", ")));
+      }
+    }
+    Ok(())
+  }
+
+  /// The level of the rule `name` for the file at `path`.
+  pub fn level(&self, name: &str, path: Option<&Path>) -> Level {
+    let mut level = self.options.rules.get(name).copied().unwrap_or_default();
+    if let Some(path) = path {
+      let path = path.to_string_lossy();
+      let path = path.strip_prefix("./").unwrap_or(&path);
+      for o in &self.options.overrides {
+        if o.files.iter().any(|f| glob(f.strip_prefix("./").unwrap_or(f), path)) {
+          level = o.rules.get(name).copied().unwrap_or(level);
+        }
+      }
+    }
+    level
+  }
+
+  /// Problems with `document`, in document order, leaving out those turned
+  /// off by directives in the document.
+  pub fn lint(&self, document: &Document) -> Vec<Diagnostic> {
+    let directives = &document.directives;
+    if directives.generated && self.options.generated == Generated::Skip {
+      return vec![];
+    }
+    let mut diagnostics = directives.invalid.clone();
+    for rule in &self.rules {
+      let severity = match self.level(rule.name(), document.path.as_deref()).severity() {
+        Some(severity) => severity,
+        None => continue,
+      };
+      for problem in rule.check(document) {
+        diagnostics.push(Diagnostic {
+          severity,
+          source: rule.name().to_owned(),
+          message: problem.message,
+          position: problem.position,
+          fix: problem.fix,
+        });
+      }
+    }
+    diagnostics.retain(|d| !directives.suppresses(d));
+    if directives.generated && self.options.generated == Generated::Relax {
+      for diagnostic in &mut diagnostics {
+        diagnostic.severity = Severity::Warning;
+      }
+    }
+    diagnostics.sort_by_key(|d| d.start().map(|p| p.offset));
+    diagnostics
+  }
+}
+
+/// `original`, the contents of the file of `document`, with the fixes of
+/// `diagnostics` made; `None` if that changes nothing. A fix that overlaps
+/// one before it is left out, to be made once the file is linted again.
+pub fn apply_fixes(document: &Document, original: &[u8], diagnostics: &[Diagnostic]) -> Option<Vec<u8>> {
+  let mut fixes = diagnostics
+    .iter()
+    .map(|d| &d.fix)
+    .filter(|fix| !fix.is_empty())
+    .collect::<Vec<&Vec<Edit>>>();
+  if fixes.is_empty() {
+    return None;
+  }
+  fixes.sort_by_key(|fix| fix[0].start);
+
+  let mut output = Vec::with_capacity(original.len());
+  let mut at = 0;
+  for fix in fixes {
+    if fix[0].start < at || fix[fix.len() - 1].end > original.len() {
+      continue;
+    }
+    for edit in fix {
+      output.extend_from_slice(&original[at..edit.start]);
+      let offset = document.source.text_offset(edit.start);
+      output.extend(document.source.encode(&edit.text, offset));
+      at = edit.end;
+    }
+  }
+  output.extend_from_slice(&original[at..]);
+  (output != original).then_some(output)
+}
+
+/// An edit replacing `len` bytes of the text `skip` bytes into `position`.
+fn replace(document: &Document, position: &Position, skip: usize, len: usize, text: String) -> Edit {
+  let source = &document.source;
+  let start = source.text_offset(position.start.offset) + skip;
+  Edit {
+    start: source.original_offset(start),
+    end: source.original_offset(start + len),
+    text,
+  }
+}
+
+/// Names of the built-in rules.
+pub const BUILTINS: &[&str] = &[
+  "heading-increment",
+  "single-h1",
+  "no-duplicate-headings",
+  "no-empty-links",
+  "image-alt-text",
+  "fenced-code-language",
+  "no-bare-urls",
+  "list-marker-style",
+];
+
+pub fn builtin(name: &str) -> Option<Box<dyn Rule>> {
+  match name {
+    "heading-increment" => Some(Box::new(HeadingIncrement)),
+    "single-h1" => Some(Box::new(SingleH1)),
+    "no-duplicate-headings" => Some(Box::new(NoDuplicateHeadings)),
+    "no-empty-links" => Some(Box::new(NoEmptyLinks)),
+    "image-alt-text" => Some(Box::new(ImageAltText)),
+    "fenced-code-language" => Some(Box::new(FencedCodeLanguage)),
+    "no-bare-urls" => Some(Box::new(NoBareUrls)),
+    "list-marker-style" => Some(Box::new(ListMarkerStyle)),
+    _ => None,
+  }
+}
+
+fn line(position: &Option<Position>) -> usize {
+  position.as_ref().map(|p| p.start.line).unwrap_or_default()
+}
+
+/// Headings nest one level at a time: no `###` right after `#`.
+pub struct HeadingIncrement;
+
+impl Rule for HeadingIncrement {
+  fn name(&self) -> &str {
+    "heading-increment"
+  }
+
+  fn check(&self, document: &Document) -> Vec<Problem> {
+    let mut problems = vec![];
+    let mut previous = None;
+    walk(&document.tree, |node, _| {
+      if let Node::Heading(heading) = node {
+        if let Some(previous) = previous.filter(|p| heading.depth > p + 1) {
+          // only ATX headings go deeper than h2, so the fix is fewer `#`s
+          let fix = heading.position.as_ref().map(|p| {
+            let text = document.source.slice(p);
+            let indent = text.len() - text.trim_start_matches(' ').len();
+            let hashes = text[indent..].len() - text[indent..].trim_start_matches('#').len();
+            replace(document, p, indent, hashes, "#".repeat(usize::from(previous + 1)))
+          });
+          problems.push(
+            Problem::new(
+              format!("heading level jumps from h{} to h{}", previous, heading.depth),
+              heading.position.clone(),
+            )
+            .with_fix(fix.into_iter().collect()),
+          );
+        }
+        previous = Some(heading.depth);
+      }
+      Control::Continue
+    });
+    problems
+  }
+}
+
+/// A document has at most one `#` heading.
+pub struct SingleH1;
+
+impl Rule for SingleH1 {
+  fn name(&self) -> &str {
+    "single-h1"
+  }
+
+  fn check(&self, document: &Document) -> Vec<Problem> {
+    let mut problems = vec![];
+    let mut first = None;
+    walk(&document.tree, |node, _| {
+      if let Node::Heading(heading) = node {
+        if heading.depth == 1 {
+          match first {
+            Some(first) => problems.push(Problem::new(
+              format!("more than one h1, the first is on line {}", first),
+              heading.position.clone(),
+            )),
+            None => first = Some(line(&heading.position)),
+          }
+        }
+      }
+      Control::Continue
+    });
+    problems
+  }
+}
+
+/// Headings under the same parent heading have different text.
+pub struct NoDuplicateHeadings;
+
+impl Rule for NoDuplicateHeadings {
+  fn name(&self) -> &str {
+    "no-duplicate-headings"
+  }
+
+  fn check(&self, document: &Document) -> Vec<Problem> {
+    let mut problems = vec![];
+    // the open headings, as (depth, index), from the outermost
+    let mut parents: Vec<(u8, usize)> = vec![];
+    let mut seen: HashMap<(Option<usize>, String), usize> = HashMap::new();
+    let mut index = 0;
+    walk(&document.tree, |node, _| {
+      if let Node::Heading(heading) = node {
+        while matches!(parents.last(), Some((depth, _)) if *depth >= heading.depth) {
+          parents.pop();
+        }
+        let text = slug::text(node).trim().to_lowercase();
+        let key = (parents.last().map(|p| p.1), text);
+        match seen.get(&key) {
+          Some(first) => problems.push(Problem::new(
+            format!("duplicate heading `{}`, also on line {}", slug::text(node).trim(), first),
+            heading.position.clone(),
+          )),
+          None => {
+            seen.insert(key, line(&heading.position));
+          }
+        }
+        parents.push((heading.depth, index));
+        index += 1;
+      }
+      Control::Continue
+    });
+    problems
+  }
+}
+
+/// Links have a URL and content.
+pub struct NoEmptyLinks;
+
+impl Rule for NoEmptyLinks {
+  fn name(&self) -> &str {
+    "no-empty-links"
+  }
+
+  fn check(&self, document: &Document) -> Vec<Problem> {
+    let mut problems = vec![];
+    walk(&document.tree, |node, _| {
+      if let Node::Link(link) = node {
+        if link.url.is_empty() || link.url == "#" {
+          problems.push(Problem::new("link has no URL".to_owned(), link.position.clone()));
+        }
+        let mut content = !slug::text(node).trim().is_empty();
+        walk(node, |child, _| {
+          if matches!(
+            child,
+            Node::Image(_) | Node::ImageReference(_) | Node::MdxJsxTextElement(_) | Node::MdxTextExpression(_)
+          ) {
+            content = true;
+            return Control::Stop;
+          }
+          Control::Continue
+        });
+        if !content {
+          problems.push(Problem::new("link has no text".to_owned(), link.position.clone()));
+        }
+      }
+      Control::Continue
+    });
+    problems
+  }
+}
+
+/// Images have alt text.
+pub struct ImageAltText;
+
+impl Rule for ImageAltText {
+  fn name(&self) -> &str {
+    "image-alt-text"
+  }
+
+  fn check(&self, document: &Document) -> Vec<Problem> {
+    let mut problems = vec![];
+    walk(&document.tree, |node, _| {
+      let (alt, position) = match node {
+        Node::Image(n) => (&n.alt, &n.position),
+        Node::ImageReference(n) => (&n.alt, &n.position),
+        _ => return Control::Continue,
+      };
+      if alt.trim().is_empty() {
+        problems.push(Problem::new("image has no alt text".to_owned(), position.clone()));
+      }
+      Control::Continue
+    });
+    problems
+  }
+}
+
+/// Fenced code blocks name their language.
+pub struct FencedCodeLanguage;
+
+impl Rule for FencedCodeLanguage {
+  fn name(&self) -> &str {
+    "fenced-code-language"
+  }
+
+  fn check(&self, document: &Document) -> Vec<Problem> {
+    let mut problems = vec![];
+    walk(&document.tree, |node, _| {
+      if let Node::Code(code) = node {
+        let fenced = code.position.as_ref().is_some_and(|p| {
+          let text = document.source.slice(p);
+          text.starts_with("```") || text.starts_with("~~~")
+        });
+        if fenced && code.lang.is_none() {
+          problems.push(Problem::new("code fence has no language".to_owned(), code.position.clone()));
+        }
+      }
+      Control::Continue
+    });
+    problems
+  }
+}
+
+/// URLs are links, not text: `<https://…>` or `[…](https://…)`.
+pub struct NoBareUrls;
+
+impl Rule for NoBareUrls {
+  fn name(&self) -> &str {
+    "no-bare-urls"
+  }
+
+  fn check(&self, document: &Document) -> Vec<Problem> {
+    let mut problems = vec![];
+    walk(&document.tree, |node, _| match node {
+      // GFM autolink literals are links only in some renderers
+      Node::Link(link) => {
+        let bare = link.position.as_ref().is_some_and(|p| {
+          let text = document.source.slice(p);
+          !text.starts_with('[') && !text.starts_with('<')
+        });
+        if bare {
+          let fix = link
+            .position
+            .as_ref()
+            .filter(|p| document.source.slice(p) == link.url)
+            .and_then(|p| link_fix(document, p, 0, &link.url));
+          problems.push(
+            Problem::new(format!("bare URL `{}`", link.url), link.position.clone()).with_fix(fix.into_iter().collect()),
+          );
+        }
+        Control::Skip
+      }
+      Node::LinkReference(_) => Control::Skip,
+      Node::Text(text) => {
+        for (start, url) in bare_urls(&text.value) {
+          // exact when the text is as written, else the whole node, which
+          // cannot be fixed
+          let exact = text.position.as_ref().filter(|p| document.source.slice(p) == text.value);
+          let position = match exact {
+            Some(p) => Some(inner_position(p, &text.value, start, start + url.len())),
+            None => text.position.clone(),
+          };
+          let fix = exact.and_then(|p| link_fix(document, p, start, url));
+          problems.push(Problem::new(format!("bare URL `{}`", url), position).with_fix(fix.into_iter().collect()));
+        }
+        Control::Continue
+      }
+      _ => Control::Continue,
+    });
+    problems
+  }
+}
+
+/// An edit turning `url`, `skip` bytes into `position`, into a link; as
+/// `[url](url)`, as MDX has no `<url>` autolinks. `None` for URLs with
+/// characters that would need escaping.
+fn link_fix(document: &Document, position: &Position, skip: usize, url: &str) -> Option<Edit> {
+  if url.contains(['(', ')', '[', ']', '\\', '<', '>', '*', '_', '`']) {
+    return None;
+  }
+  Some(replace(document, position, skip, url.len(), format!("[{}]({})", url, url)))
+}
+
+/// The `http://` and `https://` URLs in `text`, with their offsets.
+fn bare_urls(text: &str) -> Vec<(usize, &str)> {
+  let mut urls = vec![];
+  let mut from = 0;
+  while let Some(found) = ["http://", "https://"]
+    .iter()
+    .filter_map(|scheme| text[from..].find(scheme))
+    .min()
+  {
+    let start = from + found;
+    let rest = &text[start..];
+    let end = rest
+      .find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '\'' | ')' | ']'))
+      .unwrap_or(rest.len());
+    let url = rest[..end].trim_end_matches(['.', ',', ';', ':', '!', '?']);
+    if url.len() > "https://".len() {
+      urls.push((start, url));
+    }
+    from = start + end.max(1);
+  }
+  urls
+}
+
+/// The position of `value[start..end]`, where `value` is the text at `outer`.
+fn inner_position(outer: &Position, value: &str, start: usize, end: usize) -> Position {
+  let point = |at: usize| {
+    let before = &value[..at];
+    match before.rfind('\n') {
+      Some(newline) => Point::new(
+        outer.start.line + before.matches('\n').count(),
+        at - newline,
+        outer.start.offset + at,
+      ),
+      None => Point::new(outer.start.line, outer.start.column + at, outer.start.offset + at),
+    }
+  };
+  Position {
+    start: point(start),
+    end: point(end),
+  }
+}
+
+/// Bullet lists use one marker, and ordered lists one delimiter, throughout
+/// a document; the first list sets the style.
+pub struct ListMarkerStyle;
+
+impl Rule for ListMarkerStyle {
+  fn name(&self) -> &str {
+    "list-marker-style"
+  }
+
+  fn check(&self, document: &Document) -> Vec<Problem> {
+    let mut problems = vec![];
+    // the first bullet and the first delimiter, with their lines
+    let mut bullet: Option<(char, usize)> = None;
+    let mut delimiter: Option<(char, usize)> = None;
+    walk(&document.tree, |node, context| {
+      let list = match node {
+        Node::List(list) => list,
+        _ => return Control::Continue,
+      };
+      // with the marker of the list right before it, the two lists would
+      // become one
+      let follows_list = match (context.parent().and_then(|p| p.children()), context.index()) {
+        (Some(siblings), Some(index)) if index > 0 => matches!(siblings[index - 1], Node::List(_)),
+        _ => false,
+      };
+      for item in &list.children {
+        let position = match item.position() {
+          Some(position) => position,
+          None => continue,
+        };
+        let text = document.source.slice(position);
+        let digits = text.len() - text.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let marker = text[digits..].chars().next();
+        let (first, kind) = if list.ordered {
+          (&mut delimiter, "delimiter")
+        } else {
+          (&mut bullet, "marker")
+        };
+        match (marker, *first) {
+          (Some(marker), Some((expected, at))) if marker != expected => {
+            let problem = Problem::new(
+              format!("list {} `{}` differs from `{}` used on line {}", kind, marker, expected, at),
+              Some(position.clone()),
+            );
+            problems.push(if follows_list {
+              problem
+            } else {
+              problem.with_fix(vec![replace(document, position, digits, 1, expected.to_string())])
+            });
+          }
+          (Some(marker), None) => *first = Some((marker, position.start.line)),
+          _ => {}
+        }
+      }
+      Control::Continue
+    });
+    problems
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::transform::Pipeline;
+  use crate::{parse_str, Options};
+  use std::path::PathBuf;
+
+  fn lint(source: &str, options: LintOptions) -> Vec<String> {
+    let mut document = parse_str(source, &Options::default()).unwrap();
+    document.path = Some(PathBuf::from("docs/a.md"));
+    Linter::with_builtins(options)
+      .unwrap()
+      .lint(&document)
+      .into_iter()
+      .map(|d| format!("{}:{} {} {}", d.start().unwrap().line, d.severity, d.source, d.message))
+      .collect()
+  }
+
+  #[test]
+  fn runs_builtin_rules() {
+    let source = "# A\n\n### B\n\n## C\n\n### D\n\n## C\n\n### D\n\n# E\n\n\
+      [](#) ![](a.png) see https://example.com/x.\n\n```\nx\n```\n\n- a\n- b\n\n* c\n\n1. d\n2) e\n";
+    assert_eq!(
+      lint(source, LintOptions::default()),
+      vec![
+        "3:warning heading-increment heading level jumps from h1 to h3",
+        "9:warning no-duplicate-headings duplicate heading `C`, also on line 5",
+        "13:warning single-h1 more than one h1, the first is on line 1",
+        "15:warning no-empty-links link has no URL",
+        "15:warning no-empty-links link has no text",
+        "15:warning image-alt-text image has no alt text",
+        "15:warning no-bare-urls bare URL `https://example.com/x`",
+        "17:warning fenced-code-language code fence has no language",
+        "24:warning list-marker-style list marker `*` differs from `-` used on line 21",
+        "27:warning list-marker-style list delimiter `)` differs from `.` used on line 26",
+      ]
+    );
+  }
+
+  #[test]
+  fn configures_levels() {
+    let source = "# A\n\n### B\n\n# C\n";
+    let options: LintOptions = serde_json::from_str(
+      r#"{
+        "rules": { "single-h1": "error", "heading-increment": "off" },
+        "overrides": [{ "files": ["docs/*"], "rules": { "single-h1": "warning" } }, { "files": ["other/*"], "rules": { "single-h1": "off" } }]
+      }"#,
+    )
+    .unwrap();
+    assert_eq!(
+      lint(source, options),
+      vec!["5:warning single-h1 more than one h1, the first is on line 1"]
+    );
+
+    let suppressed = "<!-- @generated -->\n\n# A\n\n<!-- lint disable single-h1 -->\n\n# B\n\n<!-- lint ignore-next-line -->\n### C\n";
+    assert_eq!(lint(suppressed, LintOptions::default()), Vec::<String>::new());
+    // directives still apply when a transform removed their comments
+    let options = Options {
+      transforms: Pipeline::from_names(&["remove-comments".to_owned()]).unwrap(),
+      ..Options::default()
+    };
+    let document = parse_str(suppressed, &options).unwrap();
+    assert!(document.directives.generated);
+    assert!(Linter::with_builtins(LintOptions::default()).unwrap().lint(&document).is_empty());
+    let relax = LintOptions {
+      rules: BTreeMap::from([("heading-increment".to_owned(), Level::Error)]),
+      generated: Generated::Relax,
+      ..LintOptions::default()
+    };
+    assert_eq!(
+      lint(&suppressed.replace("<!-- lint ignore-next-line -->", ""), relax),
+      vec!["10:warning heading-increment heading level jumps from h1 to h3"]
+    );
+
+    let unknown = LintOptions {
+      rules: BTreeMap::from([("nope".to_owned(), Level::Error)]),
+      ..LintOptions::default()
+    };
+    assert!(Linter::with_builtins(unknown).is_err());
+  }
+
+  #[test]
+  fn fixes_problems() {
+    let linter = Linter::with_builtins(LintOptions::default()).unwrap();
+    let fix = |source: &str| {
+      let document = parse_str(source, &Options::default()).unwrap();
+      let diagnostics = linter.lint(&document);
+      apply_fixes(&document, source.as_bytes(), &diagnostics).map(|fixed| String::from_utf8(fixed).unwrap())
+    };
+
+    let source = "+++\ntitle = 'a'\n+++\n\n# A\n\n#### B\n\nSee http://a.com/b, <http://c.com> and\nhttp://x(y).\n\n- a\n\n* b\n\nc\n\n* d\n";
+    let fixed = fix(source).unwrap();
+    assert_eq!(
+      fixed,
+      "+++\ntitle = 'a'\n+++\n\n# A\n\n## B\n\nSee [http://a.com/b](http://a.com/b), <http://c.com> and\nhttp://x(y).\n\n- a\n\n* b\n\nc\n\n- d\n"
+    );
+    // the URL with a parenthesis is left as it is, and so is `* b`, which
+    // would join the list before it
+    assert_eq!(fix(&fixed), None);
+    // links are added, but lists are neither joined nor split
+    let count = |source: &str| {
+      let mut count = 0;
+      walk(&parse_str(source, &Options::default()).unwrap().tree, |node, _| {
+        if matches!(node, Node::List(_) | Node::ListItem(_)) {
+          count += 1;
+        }
+        Control::Continue
+      });
+      count
+    };
+    assert_eq!(count(&fixed), count(source));
+
+    // only the fixed lines change, whatever the line endings of the others
+    let mixed = "# A\r\n\n#### B\n\r\nSee http://a.com\r\n\n- a\r\n\nb\n\n* c\n";
+    assert_eq!(
+      fix(mixed).unwrap(),
+      "# A\r\n\n## B\n\r\nSee [http://a.com](http://a.com)\r\n\n- a\r\n\nb\n\n- c\n"
+    );
+  }
+}
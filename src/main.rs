@@ -1,49 +1,544 @@
 // https://doc.rust-lang.org/1.16.0/book/benchmark-tests.html
 #![feature(test)]
 
-mod wrappers;
-use wrappers::MyNode;
+use markdown_rs_test::chunk::{self, Unit};
+use markdown_rs_test::config::Config;
+use markdown_rs_test::diagnostic::{Diagnostic, Report};
+use markdown_rs_test::format;
+use markdown_rs_test::html;
+use markdown_rs_test::links::{self, Checker};
+use markdown_rs_test::lint::{self, Linter};
+use markdown_rs_test::schema::Schema;
+use markdown_rs_test::section::Sections;
+use markdown_rs_test::select::Selector;
+use markdown_rs_test::stats::Stats;
+use markdown_rs_test::tangle::{self, TangleOptions};
+use markdown_rs_test::text::to_text;
+use markdown_rs_test::transform::Pipeline;
+use markdown_rs_test::wrappers::MyNode;
+use markdown_rs_test::{collect_files, parse_file, Document, Options, SerializeOptions};
 
-use serde::Deserialize;
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use serde_json::json;
-use yaml_front_matter::YamlFrontMatter;
+use std::fs;
+use std::path::{Path, PathBuf};
 
-// Debug allows the struct to be printed
-#[derive(Deserialize, Debug)]
-struct Metadata {
-    page_title: String,
-    description: String,
+/// Serialize markdown/MDX documents to JSON
+#[derive(Parser, Debug)]
+#[command(version, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    convert: ConvertArgs,
 }
 
-fn main() -> Result<(), String> {
-    use std::env;
-    use std::fs;
+#[derive(Args, Debug)]
+struct Input {
+    /// Files or directories to process; directories are searched for `.md` and `.mdx` files
+    #[arg(default_value = "src/input.mdx")]
+    paths: Vec<PathBuf>,
+
+    /// Config file, defaults to `markdown-rs-test.json` in the working directory if present
+    #[arg(long)]
+    config: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct ConvertArgs {
+    #[command(flatten)]
+    input: Input,
+
+    /// Also write the output to this file
+    #[arg(short, long, default_value = "src/output.json")]
+    output: PathBuf,
+
+    /// Print the tree as JSON, or only its plain text
+    #[arg(long, value_enum, default_value_t = Format::Json)]
+    format: Format,
+
+    /// Report the encoding and line ending normalizations applied to each file
+    #[arg(long)]
+    normalizations: bool,
+
+    /// Include the table of contents of each document
+    #[arg(long)]
+    toc: bool,
+
+    /// Group the content of each document into nested `Section` nodes, one per heading; the `sections` transform
+    #[arg(long)]
+    sections: bool,
+
+    /// Include statistics of each document, and of all of them after the last one in batch mode
+    #[arg(long)]
+    stats: bool,
+
+    /// List the images and JSX `src`s of each document with their sizes, and check that the files exist
+    #[arg(long)]
+    assets: bool,
+}
 
-    let working_dir = env::current_dir().unwrap();
-    let file_path = working_dir.join("src/input.mdx");
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Json,
+    Text,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print the nodes matching a CSS-like selector as JSON
+    Query {
+        /// Selector, e.g. `MdxJsxFlowElement[name=Tabs] Code[lang=hcl]`
+        selector: String,
+
+        #[command(flatten)]
+        input: Input,
+    },
+    /// Print the table of contents of each document as JSON
+    Toc {
+        #[command(flatten)]
+        input: Input,
+    },
+    /// List the links of each document as JSON, and check relative links and anchors
+    Links {
+        #[command(flatten)]
+        input: Input,
+    },
+    /// Write code blocks to the files named in their fence meta, e.g. ```hcl filename="main.tf"
+    ///
+    /// Prints a manifest of the files written and the blocks they come from.
+    Tangle {
+        /// Directory to write the files in
+        #[arg(long, default_value = ".")]
+        out_dir: PathBuf,
+
+        /// Only write blocks in this language; may be repeated
+        #[arg(long)]
+        lang: Vec<String>,
 
-    // load string contents of MDX
-    let contents = fs::read_to_string(file_path).expect("Should have been able to read the file");
+        /// Only write files matching this pattern, with `*` and `?` wildcards; may be repeated
+        #[arg(long)]
+        path: Vec<String>,
 
-    // split contents into YAML frontmatter and actual content
-    let result = YamlFrontMatter::parse::<Metadata>(contents.as_str()).unwrap();
+        #[command(flatten)]
+        input: Input,
+    },
+    /// Check documents against the lint rules in the config, and report the problems found
+    Lint {
+        /// Print the problems as a report, or as JSON
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
 
-    println!("{:?}", result.metadata);
+        /// Fix the problems that can be fixed automatically in the files, and report the rest
+        #[arg(long)]
+        fix: bool,
 
-    // parse into and AST and do some json serialization
-    let mdast = markdown::to_mdast(&result.content, &markdown::ParseOptions::default())?;
-    let node: MyNode = mdast.into();
+        #[command(flatten)]
+        input: Input,
+    },
+    /// Rewrite documents in the style set in the config
+    ///
+    /// Frontmatter, encoding and line endings are kept as they are.
+    Fmt {
+        /// Do not write files; print a diff of the changes and fail if there are any
+        #[arg(long)]
+        check: bool,
 
-    let obj = json!(node);
+        #[command(flatten)]
+        input: Input,
+    },
+    /// Render documents to HTML, for previews
+    ///
+    /// JSX components render through the templates under `html.components` in the config.
+    Html {
+        /// Write each document to `<out-dir>/<path>.html` instead of printing it
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
 
-    let output_file_path = working_dir.join("src/output.json");
-    let output_json = serde_json::to_string_pretty(&obj).unwrap();
+        #[command(flatten)]
+        input: Input,
+    },
+    /// Split documents into chunks on heading boundaries, printed as JSON lines
+    Chunk {
+        /// Largest size of a chunk, overrides `chunk.budget` in the config
+        #[arg(long)]
+        budget: Option<usize>,
+
+        /// What the budget counts: `chars` or `tokens`, overrides `chunk.unit` in the config
+        #[arg(long)]
+        unit: Option<Unit>,
+
+        #[command(flatten)]
+        input: Input,
+    },
+}
+
+fn main() -> Result<(), String> {
+    run(Cli::parse())
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    match cli.command {
+        None => convert(cli.convert),
+        Some(Command::Query { selector, input }) => query(&selector, input),
+        Some(Command::Toc { input }) => toc(input),
+        Some(Command::Links { input }) => check_links(input),
+        Some(Command::Tangle {
+            out_dir,
+            lang,
+            path,
+            input,
+        }) => tangle(
+            &out_dir,
+            TangleOptions {
+                langs: lang,
+                paths: path,
+            },
+            input,
+        ),
+        Some(Command::Lint { format, fix, input }) => lint(format, fix, input),
+        Some(Command::Fmt { check, input }) => fmt(check, input),
+        Some(Command::Html { out_dir, input }) => render_html(out_dir.as_deref(), input),
+        Some(Command::Chunk {
+            budget,
+            unit,
+            input,
+        }) => chunk(budget, unit, input),
+    }
+}
+
+/// Read the config and list the files to process.
+fn load(input: &Input) -> Result<(Config, Options, Vec<PathBuf>), String> {
+    let config = Config::discover(input.config.as_deref())?;
+    let options = Options {
+        parse: config.syntax.parse_options(),
+        schema: match &config.schema {
+            Some(path) => Some(Schema::load(path)?),
+            None => None,
+        },
+        transforms: Pipeline::from_names(&config.transforms)?,
+        assets: false,
+        root: config.links.root.clone(),
+        check_code: config.check_code,
+    };
+    Ok((config, options, collect_files(&input.paths)?))
+}
+
+fn convert(args: ConvertArgs) -> Result<(), String> {
+    let (config, mut options, files) = load(&args.input)?;
+    options.assets = args.assets;
+    if args.sections && !options.transforms.names().contains(&"sections") {
+        options.transforms.push(Box::new(Sections));
+    }
+    let batch = files.len() > 1;
+    // more than one document is batch mode: one JSON document per line
+    let serialize_options = SerializeOptions {
+        pretty: !batch,
+        normalizations: args.normalizations,
+        toc: if args.toc { Some(config.toc) } else { None },
+        stats: args.stats,
+    };
+    let mut report = Report::default();
+    let mut summary = Stats::default();
+    let mut outputs = vec![];
+
+    for file_path in files {
+        let document = parse_file(&file_path, &options)?;
+        if args.stats {
+            summary.add(&document.stats());
+        }
+        let output = match args.format {
+            Format::Json => document.to_string(&serialize_options),
+            Format::Text if batch => format!(
+                "==> {} <==\n{}\n",
+                file_path.display(),
+                to_text(&document.tree, &config.text)
+            ),
+            Format::Text => to_text(&document.tree, &config.text),
+        };
+        report.add(file_path, document.diagnostics);
+
+        // Output to stdout
+        println!("{}", output);
+        outputs.push(output);
+    }
+
+    // corpus-wide statistics as one more line
+    if args.stats && batch && args.format == Format::Json {
+        let output = serde_json::to_string(&json!({ "summary": summary })).unwrap();
+        println!("{}", output);
+        outputs.push(output);
+    }
 
     // Output to local file
-    fs::write(output_file_path, output_json.clone()).unwrap();
+    fs::write(&args.output, outputs.join("\n"))
+        .map_err(|e| format!("{}: could not write file: {}", args.output.display(), e))?;
 
-    // Output to stdout
-    println!("{}", output_json.clone());
+    if options.schema.is_some() || !report.is_empty() {
+        eprintln!("{}", report);
+    }
+    if report.has_errors() {
+        return Err("validation failed".to_owned());
+    }
+
+    Ok(())
+}
+
+fn query(selector: &str, input: Input) -> Result<(), String> {
+    let selector = Selector::parse(selector)?;
+    let (_, options, files) = load(&input)?;
+    let pretty = files.len() <= 1;
+
+    for file_path in files {
+        let document = parse_file(&file_path, &options)?;
+        let matches = selector
+            .select_all(&document.tree)
+            .into_iter()
+            .map(|node| MyNode::from(node.clone()))
+            .collect::<Vec<MyNode>>();
+
+        let obj = json!({
+            "file": file_path,
+            "matches": matches,
+        });
+        if pretty {
+            println!("{}", serde_json::to_string_pretty(&obj).unwrap());
+        } else {
+            println!("{}", serde_json::to_string(&obj).unwrap());
+        }
+    }
+
+    Ok(())
+}
+
+fn toc(input: Input) -> Result<(), String> {
+    let (config, options, files) = load(&input)?;
+    let pretty = files.len() <= 1;
+
+    for file_path in files {
+        let document = parse_file(&file_path, &options)?;
+        let obj = json!({
+            "file": file_path,
+            "toc": document.toc(&config.toc),
+        });
+        if pretty {
+            println!("{}", serde_json::to_string_pretty(&obj).unwrap());
+        } else {
+            println!("{}", serde_json::to_string(&obj).unwrap());
+        }
+    }
+
+    Ok(())
+}
+
+fn check_links(input: Input) -> Result<(), String> {
+    let (config, options, files) = load(&input)?;
+    let pretty = files.len() <= 1;
+    // every document is needed up front, to check anchors in other documents
+    let documents = files
+        .iter()
+        .map(|file_path| parse_file(file_path, &options))
+        .collect::<Result<Vec<Document>, String>>()?;
+    let checker = Checker::new(&documents, &config.links);
+    let mut report = Report::default();
+
+    for document in &documents {
+        let obj = json!({
+            "file": document.path,
+            "links": links::links(&document.tree),
+        });
+        if pretty {
+            println!("{}", serde_json::to_string_pretty(&obj).unwrap());
+        } else {
+            println!("{}", serde_json::to_string(&obj).unwrap());
+        }
+        report.add(
+            document.path.clone().unwrap_or_default(),
+            checker.check(document),
+        );
+    }
+
+    eprintln!("{}", report);
+    if report.has_errors() {
+        return Err("link check failed".to_owned());
+    }
+
+    Ok(())
+}
+
+fn tangle(out_dir: &Path, tangle_options: TangleOptions, input: Input) -> Result<(), String> {
+    let (_, options, files) = load(&input)?;
+    let documents = files
+        .iter()
+        .map(|file_path| parse_file(file_path, &options))
+        .collect::<Result<Vec<Document>, String>>()?;
+
+    let outputs = tangle::tangle(&documents, &tangle_options)?;
+    tangle::write(&outputs, out_dir)?;
+
+    let manifest = json!({ "dir": out_dir, "files": outputs });
+    println!("{}", serde_json::to_string_pretty(&manifest).unwrap());
+
+    Ok(())
+}
+
+/// How many times at most `lint --fix` fixes a file and lints it again;
+/// fixes can uncover problems, such as a heading that jumps levels once the
+/// one before it is fixed.
+const FIX_PASSES: usize = 10;
+
+fn lint(format: Format, fix: bool, input: Input) -> Result<(), String> {
+    let (config, options, files) = load(&input)?;
+    let linter = Linter::with_builtins(config.lint)?;
+    let pretty = files.len() <= 1;
+    let mut report = Report::default();
+
+    for file_path in files {
+        let diagnostics = if fix {
+            fix_file(&file_path, &options, &linter)?
+        } else {
+            linter.lint(&parse_file(&file_path, &options)?)
+        };
+        if format == Format::Json {
+            let obj = json!({
+                "file": file_path,
+                "diagnostics": diagnostics,
+            });
+            if pretty {
+                println!("{}", serde_json::to_string_pretty(&obj).unwrap());
+            } else {
+                println!("{}", serde_json::to_string(&obj).unwrap());
+            }
+        }
+        report.add(file_path, diagnostics);
+    }
+
+    if format == Format::Text {
+        println!("{}", report);
+    }
+    if report.has_errors() {
+        return Err("lint failed".to_owned());
+    }
+
+    Ok(())
+}
+
+/// Fix the problems `linter` finds in the file at `path`, pass after pass
+/// until nothing changes, and return the problems left.
+fn fix_file(path: &Path, options: &Options, linter: &Linter) -> Result<Vec<Diagnostic>, String> {
+    let mut document = parse_file(path, options)?;
+    let mut diagnostics = linter.lint(&document);
+    for pass in 0..=FIX_PASSES {
+        let original = fs::read(path)
+            .map_err(|e| format!("{}: could not read file: {}", path.display(), e))?;
+        let fixed = match lint::apply_fixes(&document, &original, &diagnostics) {
+            Some(fixed) => fixed,
+            None => break,
+        };
+        if pass == FIX_PASSES {
+            diagnostics.push(Diagnostic::warning(
+                "lint",
+                format!(
+                    "fixes still change the file after {} passes, stopped fixing",
+                    FIX_PASSES
+                ),
+                None,
+            ));
+            break;
+        }
+        fs::write(path, fixed)
+            .map_err(|e| format!("{}: could not write file: {}", path.display(), e))?;
+        document = parse_file(path, options)?;
+        diagnostics = linter.lint(&document);
+    }
+    Ok(diagnostics)
+}
+
+fn fmt(check: bool, input: Input) -> Result<(), String> {
+    let (config, options, files) = load(&input)?;
+    config.fmt.validate()?;
+    let mut unformatted = 0;
+
+    for file_path in files {
+        let original = fs::read(&file_path)
+            .map_err(|e| format!("{}: could not read file: {}", file_path.display(), e))?;
+        let formatted = format::format(&original, &options.parse, &config.fmt)
+            .map_err(|e| format!("{}:{}", file_path.display(), e))?;
+        if formatted == original {
+            continue;
+        }
+        if check {
+            print!(
+                "{}",
+                format::diff(&file_path.display().to_string(), &original, &formatted)
+            );
+            unformatted += 1;
+        } else {
+            fs::write(&file_path, formatted)
+                .map_err(|e| format!("{}: could not write file: {}", file_path.display(), e))?;
+        }
+    }
+
+    if unformatted > 0 {
+        return Err(format!(
+            "formatting check failed: {} file(s) would change",
+            unformatted
+        ));
+    }
+
+    Ok(())
+}
+
+fn render_html(out_dir: Option<&Path>, input: Input) -> Result<(), String> {
+    let (config, options, files) = load(&input)?;
+    let batch = files.len() > 1;
+
+    for file_path in files {
+        let document = parse_file(&file_path, &options)?;
+        let output = html::to_html(&document, &config.html);
+        match out_dir {
+            Some(out_dir) => {
+                // the path as given, without `..` or a root, so that it stays in `out_dir`
+                let relative = file_path
+                    .components()
+                    .filter(|c| matches!(c, std::path::Component::Normal(_)))
+                    .collect::<PathBuf>();
+                let path = out_dir.join(relative).with_extension("html");
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).map_err(|e| {
+                        format!("{}: could not create directory: {}", parent.display(), e)
+                    })?;
+                }
+                fs::write(&path, output)
+                    .map_err(|e| format!("{}: could not write file: {}", path.display(), e))?;
+            }
+            None if batch => print!("==> {} <==\n{}\n", file_path.display(), output),
+            None => print!("{}", output),
+        }
+    }
+
+    Ok(())
+}
+
+fn chunk(budget: Option<usize>, unit: Option<Unit>, input: Input) -> Result<(), String> {
+    let (config, options, files) = load(&input)?;
+    let mut chunk_options = config.chunk;
+    if let Some(budget) = budget {
+        chunk_options.budget = budget;
+    }
+    if let Some(unit) = unit {
+        chunk_options.unit = unit;
+    }
+
+    for file_path in files {
+        let document = parse_file(&file_path, &options)?;
+        for chunk in chunk::chunks(&document, &chunk_options) {
+            let mut obj = json!(chunk);
+            obj["file"] = json!(file_path);
+            println!("{}", serde_json::to_string(&obj).unwrap());
+        }
+    }
 
     Ok(())
 }
@@ -54,8 +549,14 @@ mod tests {
     extern crate test;
     use super::*;
     use test::Bencher;
+    #[test]
+    fn verify_cli() {
+        use clap::CommandFactory;
+        Cli::command().debug_assert();
+    }
+
     #[bench]
     fn benchmark_test(b: &mut Bencher) {
-        b.iter(|| main());
+        b.iter(|| run(Cli::parse_from(["markdown-rs-test"])));
     }
 }
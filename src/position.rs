@@ -0,0 +1,133 @@
+use crate::source::Source;
+use crate::visit::{walk_mut, Control};
+use markdown::mdast::{AttributeContent, AttributeValue, Node, Stop};
+use markdown::unist::Point;
+
+// `markdown::to_mdast` only sees the content after the frontmatter, so every
+// point it produces is relative to where that content starts in the file.
+
+/// Move every position in `node`, parsed from text that starts at `start` in
+/// the original file, so that it points into the original file.
+pub fn shift(node: &mut Node, start: &Point) {
+  if start.offset == 0 {
+    return;
+  }
+  map(node, &|point: &mut Point| {
+    if point.line == 1 {
+      point.column += start.column - 1;
+    }
+    point.line += start.line - 1;
+    point.offset += start.offset;
+  });
+}
+
+/// Turn offsets into the normalized text of `source` into offsets on disk.
+pub fn remap(node: &mut Node, source: &Source) {
+  if source.is_identity() {
+    return;
+  }
+  map(node, &|point: &mut Point| point.offset = source.original_offset(point.offset));
+}
+
+/// Apply `f` to every point in `node`; MDX stops, which map offsets in a
+/// value to offsets in the document, are updated along with them.
+fn map(node: &mut Node, f: &dyn Fn(&mut Point)) {
+  walk_mut(node, |node, _| {
+    if let Some(position) = node.position_mut() {
+      f(&mut position.start);
+      f(&mut position.end);
+    }
+
+    match node {
+      Node::MdxjsEsm(n) => map_stops(&mut n.stops, f),
+      Node::MdxFlowExpression(n) => map_stops(&mut n.stops, f),
+      Node::MdxTextExpression(n) => map_stops(&mut n.stops, f),
+      Node::MdxJsxFlowElement(n) => map_attributes(&mut n.attributes, f),
+      Node::MdxJsxTextElement(n) => map_attributes(&mut n.attributes, f),
+      _ => {}
+    }
+
+    Control::Continue
+  });
+}
+
+fn map_stops(stops: &mut [Stop], f: &dyn Fn(&mut Point)) {
+  for stop in stops {
+    // only the offset of a point is used, the line is a placeholder
+    let mut point = Point::new(0, 1, stop.1);
+    f(&mut point);
+    stop.1 = point.offset;
+  }
+}
+
+fn map_attributes(attributes: &mut [AttributeContent], f: &dyn Fn(&mut Point)) {
+  for attribute in attributes {
+    match attribute {
+      AttributeContent::Expression(_, stops) => map_stops(stops, f),
+      AttributeContent::Property(p) => {
+        if let Some(AttributeValue::Expression(_, stops)) = &mut p.value {
+          map_stops(stops, f)
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::frontmatter;
+  use crate::wrappers::MyNode;
+  use serde_json::{json, Value};
+
+  const SOURCE: &str = "---\npage_title: A title\ndescription: >-\n  Text\n---\n\n# Hello *world*\n\nSome `code` and [a link](https://example.com).\n";
+
+  fn parse(source: &str) -> Value {
+    let split = frontmatter::split(source).unwrap();
+    let mut tree = markdown::to_mdast(split.content, &markdown::ParseOptions::default()).unwrap();
+    shift(&mut tree, &split.content_start);
+    json!(MyNode::from(tree))
+  }
+
+  // Collect every serialized node that has a `value` and a `position`.
+  fn values(node: &Value, found: &mut Vec<(String, usize, usize, usize, usize)>) {
+    if let (Some(value), Some(position)) = (node["value"].as_str(), node.get("position")) {
+      found.push((
+        value.to_owned(),
+        position["start"]["offset"].as_u64().unwrap() as usize,
+        position["end"]["offset"].as_u64().unwrap() as usize,
+        position["start"]["line"].as_u64().unwrap() as usize,
+        position["start"]["column"].as_u64().unwrap() as usize,
+      ));
+    }
+    if let Some(children) = node["children"].as_array() {
+      for child in children {
+        values(child, found);
+      }
+    }
+  }
+
+  #[test]
+  fn offsets_point_into_the_original_source() {
+    let mut found = vec![];
+    values(&parse(SOURCE), &mut found);
+    assert_eq!(found.len(), 7);
+
+    for (value, start, end, line, column) in found {
+      let slice = &SOURCE[start..end];
+      assert!(slice.contains(value.as_str()), "{:?} not in {:?}", value, slice);
+
+      let before = &SOURCE[..start];
+      assert_eq!(line, before.matches('\n').count() + 1);
+      assert_eq!(column, start - before.rfind('\n').map_or(0, |i| i + 1) + 1);
+    }
+  }
+
+  #[test]
+  fn root_spans_the_content() {
+    let tree = parse(SOURCE);
+    assert_eq!(tree["position"]["start"]["line"], 6);
+    assert_eq!(tree["position"]["end"]["offset"], SOURCE.len());
+    assert_eq!(tree["children"][0]["position"]["start"]["line"], 7);
+  }
+}
@@ -0,0 +1,290 @@
+//! Match references to the definitions they use.
+
+use crate::diagnostic::Diagnostic;
+use crate::source::Source;
+use crate::visit::{walk, Control};
+use markdown::mdast::Node;
+use markdown::unist::{Point, Position};
+use markdown::Constructs;
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// What a reference points to.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Resolution {
+  /// A `LinkReference` or `ImageReference` to a `Definition`.
+  Definition { url: String, title: Option<String> },
+  /// A `FootnoteReference` to the `index`th `FootnoteDefinition` of the
+  /// document, counting from 0.
+  Footnote { index: usize },
+}
+
+/// Normalize a label the way CommonMark matches them: trimmed, whitespace
+/// collapsed and case folded.
+pub fn normalize_identifier(value: &str) -> String {
+  value
+    .split_whitespace()
+    .collect::<Vec<&str>>()
+    .join(" ")
+    .to_lowercase()
+    .to_uppercase()
+}
+
+/// Resolve every reference in `tree`, parsed from `source` with
+/// `constructs`; the result has one entry per `LinkReference`,
+/// `ImageReference` and `FootnoteReference`, in document order.
+///
+/// Markdown only makes a reference when its label is defined, so what would
+/// have been a full or collapsed reference, or a footnote reference when GFM
+/// footnotes are on, to a missing definition is still text; those are
+/// reported in `diagnostics`. A lone `[label]` is not, as brackets are common
+/// in prose. References whose definition is gone anyway, such as when a
+/// transform removed it, are `None` and reported too.
+pub fn resolve(
+  tree: &Node,
+  source: &Source,
+  constructs: &Constructs,
+  diagnostics: &mut Vec<Diagnostic>,
+) -> Vec<Option<Resolution>> {
+  let footnotes_on = constructs.gfm_label_start_footnote;
+  let mut definitions = HashMap::new();
+  let mut footnotes = HashMap::new();
+  walk(tree, |node, _| {
+    match node {
+      // the first definition of a label wins
+      Node::Definition(d) => {
+        definitions.entry(normalize_identifier(&d.identifier)).or_insert(Resolution::Definition {
+          url: d.url.clone(),
+          title: d.title.clone(),
+        });
+      }
+      Node::FootnoteDefinition(d) => {
+        let index = footnotes.len();
+        footnotes.entry(normalize_identifier(&d.identifier)).or_insert(Resolution::Footnote { index });
+      }
+      _ => {}
+    }
+    Control::Continue
+  });
+
+  let mut resolutions = vec![];
+  walk(tree, |node, _| {
+    let (kind, identifier, label, table, position) = match node {
+      Node::LinkReference(r) => ("link", &r.identifier, &r.label, &definitions, &r.position),
+      Node::ImageReference(r) => ("image", &r.identifier, &r.label, &definitions, &r.position),
+      Node::FootnoteReference(r) => ("footnote", &r.identifier, &r.label, &footnotes, &r.position),
+      Node::Text(text) => {
+        if let Some(position) = &text.position {
+          for (kind, label, position) in unmatched(source, position, footnotes_on) {
+            let table = if kind == "footnote" { &footnotes } else { &definitions };
+            if !table.contains_key(&normalize_identifier(&label)) {
+              diagnostics.push(missing(kind, &label, Some(position)));
+            }
+          }
+        }
+        return Control::Continue;
+      }
+      _ => return Control::Continue,
+    };
+    let resolution = table.get(&normalize_identifier(identifier)).cloned();
+    if resolution.is_none() {
+      diagnostics.push(missing(kind, label.as_deref().unwrap_or(identifier), position.clone()));
+    }
+    resolutions.push(resolution);
+    Control::Continue
+  });
+  resolutions
+}
+
+fn missing(kind: &str, label: &str, position: Option<Position>) -> Diagnostic {
+  Diagnostic::warning(
+    "resolve",
+    format!("no definition for {} reference `{}`", kind, label),
+    position,
+  )
+}
+
+/// What looks like a reference in the text at `position`: `[text][label]`,
+/// `[label][]`, the same after `!`, and `[^label]` if `footnotes` is set,
+/// with the kind, label and position of each.
+fn unmatched(source: &Source, position: &Position, footnotes: bool) -> Vec<(&'static str, String, Position)> {
+  // the text as written, so that escaped brackets stay escaped
+  let raw = source.slice(position);
+  let start = source.text_offset(position.start.offset);
+  let point = |at: usize| {
+    let before = &raw[..at];
+    let line = position.start.line + before.matches('\n').count();
+    let column = match before.rfind('\n') {
+      Some(newline) => at - newline,
+      None => position.start.column + at,
+    };
+    Point::new(line, column, source.original_offset(start + at))
+  };
+
+  let mut found = vec![];
+  let mut from = 0;
+  while let Some((open, close)) = brackets(raw, from) {
+    let image = raw[..open].ends_with('!');
+    let first = &raw[open + 1..close];
+    let mut end = close + 1;
+    let (kind, label) = match brackets(raw, end).filter(|(second, _)| *second == end) {
+      // `[text][label]`, or `[label][]`
+      Some((second, second_close)) => {
+        end = second_close + 1;
+        let label = if second_close > second + 1 { &raw[second + 1..second_close] } else { first };
+        (if image { "image" } else { "link" }, label)
+      }
+      None => match first.strip_prefix('^') {
+        Some(label) if footnotes && !image => ("footnote", label),
+        // a lone `[label]` is as likely to be `[sic]` or `array[0]`
+        _ => {
+          from = end;
+          continue;
+        }
+      },
+    };
+    from = end;
+    // `[text](url)` that did not make a link is not a reference
+    if label.trim().is_empty() || raw[end..].starts_with('(') {
+      continue;
+    }
+
+    let begin = if image { open - 1 } else { open };
+    found.push((
+      kind,
+      label.to_owned(),
+      Position {
+        start: point(begin),
+        end: point(end),
+      },
+    ));
+  }
+  found
+}
+
+/// The first `[`, not escaped, at or after `from` in `raw`, and the `]` that
+/// closes it on the same line.
+fn brackets(raw: &str, from: usize) -> Option<(usize, usize)> {
+  let bytes = raw.as_bytes();
+  let mut open = None;
+  let mut index = from;
+  while index < bytes.len() {
+    match bytes[index] {
+      b'\\' => index += 1,
+      b'[' => open = Some(index),
+      b'\n' => open = None,
+      b']' if open.is_some() => return open.map(|open| (open, index)),
+      _ => {}
+    }
+    index += 1;
+  }
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::Syntax;
+  use markdown::mdast::{LinkReference, ReferenceKind};
+
+  fn parse(source: &str) -> Node {
+    let options = Syntax {
+      gfm: true,
+      ..Syntax::default()
+    }
+    .parse_options();
+    markdown::to_mdast(source, &options).unwrap()
+  }
+
+  #[test]
+  fn resolves_references() {
+    let source = "[a][Foo  Bar] ![b][c] x[^n]\n\n[foo bar]: /one \"One\"\n[FOO BAR]: /two\n[c]: c.png\n\n[^n]: note\n";
+    let mut tree = parse(source);
+
+    // a reference left without its definition, e.g. by a transform
+    if let Node::Paragraph(p) = &mut tree.children_mut().unwrap()[0] {
+      p.children.push(Node::LinkReference(LinkReference {
+        children: vec![],
+        position: None,
+        reference_kind: ReferenceKind::Shortcut,
+        identifier: "gone".into(),
+        label: Some("Gone".into()),
+      }));
+    }
+
+    let mut diagnostics = vec![];
+    let resolutions = resolve(&tree, &Source::decode(source.as_bytes()).unwrap(), &Constructs::gfm(), &mut diagnostics);
+    assert_eq!(
+      resolutions,
+      vec![
+        Some(Resolution::Definition {
+          url: "/one".into(),
+          title: Some("One".into())
+        }),
+        Some(Resolution::Definition {
+          url: "c.png".into(),
+          title: None
+        }),
+        Some(Resolution::Footnote { index: 0 }),
+        None,
+      ]
+    );
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "no definition for link reference `Gone`");
+  }
+
+  #[test]
+  fn reports_missing_definitions() {
+    let source = "See [a], [text][b], ![c][] and \\[d].\n\n> more[^e]\n> [f](not a url) [ ]\n\n[^g]\n\n[g]: /g\n";
+    let tree = parse(source);
+    let mut diagnostics = vec![];
+    let resolutions = resolve(&tree, &Source::decode(source.as_bytes()).unwrap(), &Constructs::gfm(), &mut diagnostics);
+    assert_eq!(resolutions, vec![]);
+
+    let found = diagnostics
+      .iter()
+      .map(|d| {
+        let position = d.position.as_ref().unwrap();
+        (
+          d.message.as_str(),
+          &source[position.start.offset..position.end.offset],
+          position.start.line,
+          position.start.column,
+        )
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(
+      found,
+      vec![
+        ("no definition for link reference `b`", "[text][b]", 1, 10),
+        ("no definition for image reference `c`", "![c][]", 1, 21),
+        ("no definition for footnote reference `e`", "[^e]", 3, 7),
+        ("no definition for footnote reference `g`", "[^g]", 6, 1),
+      ]
+    );
+  }
+
+  #[test]
+  fn leaves_bracketed_prose_alone() {
+    let source = "He said [sic] that array[0] is set.\n\n- [x] item\n- [ ] other\n\nA note[^n] and [a] [b]\n";
+    let check = |constructs: &Constructs| {
+      let tree = markdown::to_mdast(
+        source,
+        &markdown::ParseOptions {
+          constructs: constructs.clone(),
+          ..markdown::ParseOptions::default()
+        },
+      )
+      .unwrap();
+      let mut diagnostics = vec![];
+      resolve(&tree, &Source::decode(source.as_bytes()).unwrap(), constructs, &mut diagnostics);
+      diagnostics.into_iter().map(|d| d.message).collect::<Vec<String>>()
+    };
+
+    // without GFM, `[^n]` is not a footnote either
+    assert!(check(&Constructs::default()).is_empty());
+    assert_eq!(check(&Constructs::gfm()), vec!["no definition for footnote reference `n`"]);
+  }
+}
@@ -0,0 +1,101 @@
+use crate::diagnostic::Diagnostic;
+use crate::frontmatter::{Frontmatter, Segment};
+
+use jsonschema::{error::ValidationErrorKind, paths::PathChunk, JSONSchema};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// A compiled JSON Schema that document frontmatter is checked against.
+pub struct Schema {
+  compiled: JSONSchema,
+}
+
+impl Schema {
+  pub fn load(path: &Path) -> Result<Schema, String> {
+    let contents =
+      fs::read_to_string(path).map_err(|e| format!("{}: could not read schema: {}", path.display(), e))?;
+    let json: Value =
+      serde_json::from_str(&contents).map_err(|e| format!("{}: invalid schema: {}", path.display(), e))?;
+    Schema::compile(&json).map_err(|e| format!("{}: {}", path.display(), e))
+  }
+
+  pub fn compile(schema: &Value) -> Result<Schema, String> {
+    let compiled = JSONSchema::compile(schema).map_err(|e| format!("invalid schema: {}", e))?;
+    Ok(Schema { compiled })
+  }
+
+  /// Validate the frontmatter of a document; a document without frontmatter
+  /// is treated as having an empty mapping.
+  ///
+  /// Every error is reported at the key it concerns.
+  pub fn validate(&self, frontmatter: Option<&Frontmatter>) -> Vec<Diagnostic> {
+    let empty = Value::Object(Default::default());
+    let metadata = frontmatter.map_or(&empty, |f| &f.data);
+    let errors = match self.compiled.validate(metadata) {
+      Ok(()) => return vec![],
+      Err(errors) => errors,
+    };
+
+    errors
+      .map(|error| {
+        let path = error
+          .instance_path
+          .iter()
+          .map(|chunk| match chunk {
+            PathChunk::Property(p) => Segment::Key(p.to_string()),
+            PathChunk::Index(i) => Segment::Index(*i),
+            PathChunk::Keyword(k) => Segment::Key(k.to_string()),
+          })
+          .collect::<Vec<Segment>>();
+
+        let location = match error.instance_path.to_string().as_str() {
+          "" => "frontmatter".to_owned(),
+          pointer => pointer.trim_start_matches('/').replace('/', "."),
+        };
+        let message = match &error.kind {
+          ValidationErrorKind::Required { property } => {
+            format!("{}: missing required property {}", location, property)
+          }
+          _ => format!("{}: {}", location, error),
+        };
+
+        Diagnostic::error("schema", message, frontmatter.map(|f| f.locate(&path)))
+      })
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::frontmatter;
+  use serde_json::json;
+
+  #[test]
+  fn reports_errors_at_keys() {
+    let schema = Schema::compile(&json!({
+      "type": "object",
+      "required": ["page_title", "description"],
+      "properties": {
+        "page_title": { "type": "string", "maxLength": 5 },
+        "tags": { "type": "array", "items": { "enum": ["one"] } }
+      }
+    }))
+    .unwrap();
+    let source = "---\npage_title: A title\ntags:\n  - one\n  - two\n---\n";
+    let frontmatter = frontmatter::split(source).unwrap().frontmatter;
+
+    let diagnostics = schema.validate(frontmatter.as_ref());
+    let mut lines = diagnostics
+      .iter()
+      .map(|d| d.start().unwrap().line)
+      .collect::<Vec<usize>>();
+    lines.sort();
+    assert_eq!(lines, vec![1, 2, 5]);
+
+    let missing = schema.validate(None);
+    assert_eq!(missing.len(), 2);
+    assert!(missing.iter().all(|d| d.position.is_none()));
+  }
+}
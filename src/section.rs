@@ -0,0 +1,195 @@
+//! The `sections` transform: every heading directly in the root takes the
+//! content after it, up to the next heading of the same or a higher rank,
+//! into a section.
+//!
+//! ```md
+//! intro
+//! # A
+//! a
+//! ## B
+//! b
+//! # C
+//! ```
+//!
+//! becomes `intro`, `Section(# A, a, Section(## B, b))`, `Section(# C)`.
+//!
+//! mdast has no node for a section, so in the tree a section is a JSX
+//! fragment with a `depth` attribute, which MDX cannot produce: fragments
+//! have no attributes. `is_section` tells them apart. In the JSON output
+//! they are `Section` nodes with the `depth` and `id` of their heading.
+
+use crate::diagnostic::Diagnostic;
+use crate::transform::Transform;
+use crate::wrappers::{MyNode, MyPosition};
+use markdown::mdast::{AttributeContent, AttributeValue, MdxJsxAttribute, MdxJsxFlowElement, Node};
+use markdown::unist::Position;
+
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+/// Group the children of the root into sections, one per heading.
+pub struct Sections;
+
+impl Transform for Sections {
+  fn name(&self) -> &str {
+    "sections"
+  }
+
+  fn transform(&self, tree: &mut Node, _diagnostics: &mut Vec<Diagnostic>) {
+    let children = match tree.children_mut() {
+      Some(children) => children,
+      None => return,
+    };
+    let mut roots = vec![];
+    // sections whose content is still being collected, shallowest first
+    let mut open: Vec<(u8, MdxJsxFlowElement)> = vec![];
+
+    fn close(section: MdxJsxFlowElement, open: &mut [(u8, MdxJsxFlowElement)], roots: &mut Vec<Node>) {
+      match open.last_mut() {
+        Some((_, parent)) => push(parent, Node::MdxJsxFlowElement(section)),
+        None => roots.push(Node::MdxJsxFlowElement(section)),
+      }
+    }
+
+    for child in children.drain(..) {
+      if let Node::Heading(heading) = &child {
+        let depth = heading.depth;
+        while matches!(open.last(), Some((d, _)) if *d >= depth) {
+          let (_, done) = open.pop().unwrap();
+          close(done, &mut open, &mut roots);
+        }
+        let section = MdxJsxFlowElement {
+          position: heading.position.clone(),
+          name: None,
+          attributes: vec![AttributeContent::Property(MdxJsxAttribute {
+            name: "depth".to_owned(),
+            value: Some(AttributeValue::Literal(depth.to_string())),
+          })],
+          children: vec![child],
+        };
+        open.push((depth, section));
+        continue;
+      }
+      match open.last_mut() {
+        Some((_, section)) => push(section, child),
+        None => roots.push(child),
+      }
+    }
+    while let Some((_, done)) = open.pop() {
+      close(done, &mut open, &mut roots);
+    }
+    *children = roots;
+  }
+}
+
+/// Add `child` to `section`, which then ends where `child` ends.
+fn push(section: &mut MdxJsxFlowElement, child: Node) {
+  if let (Some(own), Some(other)) = (&mut section.position, child.position()) {
+    own.end = other.end.clone();
+  }
+  section.children.push(child);
+}
+
+/// Whether `node` is a section made by `Sections`.
+pub fn is_section(node: &Node) -> bool {
+  matches!(node, Node::MdxJsxFlowElement(n) if is_section_element(n))
+}
+
+pub(crate) fn is_section_element(node: &MdxJsxFlowElement) -> bool {
+  node.name.is_none() && !node.attributes.is_empty()
+}
+
+/// A section, as serialized; its `id` is added with those of the headings.
+#[derive(Debug)]
+pub struct Section {
+  /// Depth of the heading.
+  pub depth: u8,
+  /// The heading, the content after it, then subsections.
+  pub children: Vec<MyNode>,
+  /// From the start of the heading to the end of the last node in the
+  /// section.
+  pub position: Option<Position>,
+}
+
+impl From<MdxJsxFlowElement> for Section {
+  fn from(n: MdxJsxFlowElement) -> Section {
+    let depth = match n.children.first() {
+      Some(Node::Heading(heading)) => heading.depth,
+      _ => 0,
+    };
+    Section {
+      depth,
+      children: n.children.into_iter().map(MyNode::from).collect(),
+      position: n.position,
+    }
+  }
+}
+
+impl Serialize for Section {
+  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    let mut state = serializer.serialize_struct("Section", 4)?;
+    state.serialize_field("type", "Section")?;
+    state.serialize_field("depth", &self.depth)?;
+    state.serialize_field("children", &self.children)?;
+    if let Some(p) = self.position.clone() {
+      let pos: MyPosition = MyPosition::from(p);
+      state.serialize_field("position", &pos)?;
+    }
+    state.end()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::transform::Pipeline;
+  use crate::{parse_str, Options, SerializeOptions};
+  use serde_json::Value;
+
+  #[test]
+  fn groups_content_under_headings() {
+    let source = "intro\n\n# A\n\na\n\n> # quoted\n\n## B\n\nb\n\n# C\n";
+    let options = Options {
+      transforms: Pipeline::from_names(&["sections".to_owned()]).unwrap(),
+      ..Options::default()
+    };
+    let document = parse_str(source, &options).unwrap();
+    let sections = document.to_json(&SerializeOptions::default())["tree"]["children"].clone();
+
+    fn outline(node: &Value) -> String {
+      match node["type"].as_str().unwrap() {
+        "Section" => {
+          let children = node["children"].as_array().unwrap();
+          let inner = children[1..].iter().map(outline).collect::<Vec<String>>();
+          format!("{}({})", node["id"].as_str().unwrap(), inner.join(", "))
+        }
+        other => other.to_owned(),
+      }
+    }
+    let outlines = sections.as_array().unwrap().iter().map(outline).collect::<Vec<String>>();
+    assert_eq!(outlines, vec!["Paragraph", "a(Paragraph, BlockQuote, b(Paragraph))", "c()"]);
+    assert_eq!(sections[1]["depth"], 1);
+
+    let span = |section: &Value| {
+      let start = section["position"]["start"]["offset"].as_u64().unwrap() as usize;
+      let end = section["position"]["end"]["offset"].as_u64().unwrap() as usize;
+      &source[start..end]
+    };
+    assert_eq!(span(&sections[1]), "# A\n\na\n\n> # quoted\n\n## B\n\nb");
+    assert_eq!(span(&sections[2]), "# C");
+
+    // the rest of the crate sees sections in the tree
+    let found = crate::select::select_all("Section[depth=2] > Paragraph", &document.tree).unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(document.ids, vec!["a", "quoted", "b", "c"]);
+    let html = crate::html::to_html(&document, &Default::default());
+    assert!(html.starts_with("<p>intro</p>\n<section>\n<h1 id=\"a\">A</h1>\n<p>a</p>"), "{}", html);
+    let plain = parse_str(source, &Options::default()).unwrap();
+    let texts = |document: &crate::Document| {
+      let chunks = crate::chunk::chunks(document, &Default::default());
+      chunks.into_iter().map(|c| c.text).collect::<Vec<String>>()
+    };
+    assert_eq!(texts(&document), texts(&plain));
+  }
+}
@@ -0,0 +1,631 @@
+//! CSS-like selectors over mdast, in the spirit of `unist-util-select`.
+//!
+//! Supported:
+//!
+//! * types: `Heading`, `code`, `*` (case-insensitive, see `visit::type_name`)
+//! * attributes: `[lang]`, `[lang=hcl]`, `[url^="https:"]`, `[url$=".png"]`,
+//!   `[value*=TODO]`; fields of the node such as `depth`, `lang`, `meta`,
+//!   `url` or `name`, and for JSX elements also their attributes
+//! * combinators: descendant (` `), child (`>`), adjacent sibling (`+`) and
+//!   general sibling (`~`)
+//! * `:first-child`, `:last-child`, `:has(...)` and `:not(...)`
+//! * selector lists: `Link, Image`
+//!
+//! ```
+//! use markdown_rs_test::select::select_all;
+//!
+//! let tree = markdown::to_mdast("***\n\n## a\n\n## b\n", &markdown::ParseOptions::default())?;
+//! assert_eq!(select_all("ThematicBreak + Heading[depth=2]", &tree)?.len(), 1);
+//! # Ok::<(), String>(())
+//! ```
+
+use crate::visit::{type_name, walk, Control};
+use markdown::mdast::{AttributeContent, AttributeValue, Node};
+
+use std::ops::Range;
+use std::str::FromStr;
+
+/// A parsed selector list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selector(Vec<Complex>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Combinator {
+  Descendant,
+  Child,
+  Adjacent,
+  Sibling,
+}
+
+/// Compounds joined by combinators; each compound is related to the one
+/// before it by its combinator. The combinator of the first compound relates
+/// it to the scope of a `:has()`, and is a descendant combinator otherwise.
+#[derive(Debug, Clone, PartialEq)]
+struct Complex(Vec<(Combinator, Compound)>);
+
+#[derive(Debug, Clone, PartialEq, Default)]
+struct Compound {
+  name: Option<String>,
+  attributes: Vec<Attribute>,
+  pseudos: Vec<Pseudo>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operator {
+  Exists,
+  Equals(String),
+  Prefix(String),
+  Suffix(String),
+  Contains(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Attribute {
+  name: String,
+  operator: Operator,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Pseudo {
+  FirstChild,
+  LastChild,
+  Has(Selector),
+  Not(Selector),
+}
+
+impl Selector {
+  pub fn parse(selector: &str) -> Result<Selector, String> {
+    let mut parser = Parser {
+      chars: selector.chars().collect(),
+      index: 0,
+    };
+    let list = parser.list(false)?;
+    if parser.index < parser.chars.len() {
+      return Err(parser.error("unexpected character"));
+    }
+    Ok(list)
+  }
+}
+
+impl FromStr for Selector {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Selector, String> {
+    Selector::parse(s)
+  }
+}
+
+struct Parser {
+  chars: Vec<char>,
+  index: usize,
+}
+
+impl Parser {
+  fn error(&self, message: &str) -> String {
+    let near = self.chars.get(self.index).map_or("end".to_owned(), |c| format!("`{}`", c));
+    format!("invalid selector: {} at {} (column {})", message, near, self.index + 1)
+  }
+
+  fn peek(&self) -> Option<char> {
+    self.chars.get(self.index).copied()
+  }
+
+  fn whitespace(&mut self) -> bool {
+    let start = self.index;
+    while matches!(self.peek(), Some(c) if c.is_whitespace()) {
+      self.index += 1;
+    }
+    self.index > start
+  }
+
+  fn eat(&mut self, c: char) -> bool {
+    if self.peek() == Some(c) {
+      self.index += 1;
+      true
+    } else {
+      false
+    }
+  }
+
+  fn ident(&mut self) -> String {
+    let start = self.index;
+    while matches!(self.peek(), Some(c) if c.is_alphanumeric() || c == '-' || c == '_' || c == '.') {
+      self.index += 1;
+    }
+    self.chars[start..self.index].iter().collect()
+  }
+
+  // Comma-separated complex selectors; inside `:has()`/`:not()` up to `)`.
+  fn list(&mut self, nested: bool) -> Result<Selector, String> {
+    let mut list = vec![];
+    loop {
+      list.push(self.complex()?);
+      self.whitespace();
+      if self.eat(',') {
+        continue;
+      }
+      if nested && self.peek() != Some(')') {
+        return Err(self.error("expected `)`"));
+      }
+      return Ok(Selector(list));
+    }
+  }
+
+  fn combinator(&mut self) -> Option<Combinator> {
+    let combinator = match self.peek() {
+      Some('>') => Some(Combinator::Child),
+      Some('+') => Some(Combinator::Adjacent),
+      Some('~') => Some(Combinator::Sibling),
+      _ => None,
+    };
+    if combinator.is_some() {
+      self.index += 1;
+    }
+    combinator
+  }
+
+  fn complex(&mut self) -> Result<Complex, String> {
+    self.whitespace();
+    let mut combinator = self.combinator().unwrap_or(Combinator::Descendant);
+    let mut compounds = vec![];
+    loop {
+      self.whitespace();
+      compounds.push((combinator, self.compound()?));
+      let space = self.whitespace();
+      combinator = match self.combinator() {
+        Some(c) => c,
+        None if space && !matches!(self.peek(), None | Some(',') | Some(')')) => Combinator::Descendant,
+        None => return Ok(Complex(compounds)),
+      };
+    }
+  }
+
+  fn compound(&mut self) -> Result<Compound, String> {
+    let mut compound = Compound::default();
+    if self.eat('*') {
+      compound.name = Some("*".to_owned());
+    } else {
+      let name = self.ident();
+      if !name.is_empty() {
+        compound.name = Some(name);
+      }
+    }
+
+    loop {
+      if self.eat('[') {
+        compound.attributes.push(self.attribute()?);
+      } else if self.eat(':') {
+        compound.pseudos.push(self.pseudo()?);
+      } else {
+        break;
+      }
+    }
+
+    if compound == Compound::default() {
+      return Err(self.error("expected a selector"));
+    }
+    Ok(compound)
+  }
+
+  fn attribute(&mut self) -> Result<Attribute, String> {
+    self.whitespace();
+    let name = self.ident();
+    if name.is_empty() {
+      return Err(self.error("expected an attribute name"));
+    }
+    self.whitespace();
+
+    let kind = match self.peek() {
+      Some(c @ ('^' | '$' | '*')) => {
+        self.index += 1;
+        Some(c)
+      }
+      Some('=') => Some('='),
+      _ => None,
+    };
+    let operator = match kind {
+      None => Operator::Exists,
+      Some(kind) => {
+        if !self.eat('=') {
+          return Err(self.error("expected `=`"));
+        }
+        self.whitespace();
+        let value = self.value()?;
+        match kind {
+          '^' => Operator::Prefix(value),
+          '$' => Operator::Suffix(value),
+          '*' => Operator::Contains(value),
+          _ => Operator::Equals(value),
+        }
+      }
+    };
+
+    self.whitespace();
+    if !self.eat(']') {
+      return Err(self.error("expected `]`"));
+    }
+    Ok(Attribute { name, operator })
+  }
+
+  fn value(&mut self) -> Result<String, String> {
+    match self.peek() {
+      Some(quote @ ('"' | '\'')) => {
+        self.index += 1;
+        let start = self.index;
+        while matches!(self.peek(), Some(c) if c != quote) {
+          self.index += 1;
+        }
+        let value = self.chars[start..self.index].iter().collect();
+        if !self.eat(quote) {
+          return Err(self.error("unterminated string"));
+        }
+        Ok(value)
+      }
+      _ => {
+        let value = self.ident();
+        if value.is_empty() {
+          return Err(self.error("expected a value"));
+        }
+        Ok(value)
+      }
+    }
+  }
+
+  fn pseudo(&mut self) -> Result<Pseudo, String> {
+    let name = self.ident();
+    match name.as_str() {
+      "first-child" => Ok(Pseudo::FirstChild),
+      "last-child" => Ok(Pseudo::LastChild),
+      "has" | "not" => {
+        if !self.eat('(') {
+          return Err(self.error("expected `(`"));
+        }
+        let list = self.list(true)?;
+        self.eat(')');
+        Ok(if name == "has" {
+          Pseudo::Has(list)
+        } else {
+          Pseudo::Not(list)
+        })
+      }
+      _ => Err(self.error(&format!("unsupported pseudo-class `:{}`", name))),
+    }
+  }
+}
+
+/// The value of attribute `name` of `node`, as a string.
+fn attribute(node: &Node, name: &str) -> Option<String> {
+  fn jsx(attributes: &[AttributeContent], name: &str) -> Option<String> {
+    attributes.iter().find_map(|a| match a {
+      AttributeContent::Property(p) if p.name == name => Some(match &p.value {
+        Some(AttributeValue::Literal(value)) => value.clone(),
+        Some(AttributeValue::Expression(value, _)) => value.clone(),
+        None => String::new(),
+      }),
+      _ => None,
+    })
+  }
+
+  let value = match (node, name) {
+    (Node::Text(n), "value") => n.value.clone(),
+    (Node::InlineCode(n), "value") => n.value.clone(),
+    (Node::InlineMath(n), "value") => n.value.clone(),
+    (Node::Html(n), "value") => n.value.clone(),
+    (Node::Yaml(n), "value") => n.value.clone(),
+    (Node::Toml(n), "value") => n.value.clone(),
+    (Node::MdxjsEsm(n), "value") => n.value.clone(),
+    (Node::MdxFlowExpression(n), "value") => n.value.clone(),
+    (Node::MdxTextExpression(n), "value") => n.value.clone(),
+    (Node::Code(n), "value") => n.value.clone(),
+    (Node::Code(n), "lang") => n.lang.clone()?,
+    (Node::Code(n), "meta") => n.meta.clone()?,
+    (Node::Math(n), "value") => n.value.clone(),
+    (Node::Math(n), "meta") => n.meta.clone()?,
+    (Node::Heading(n), "depth") => n.depth.to_string(),
+    (Node::List(n), "ordered") => n.ordered.to_string(),
+    (Node::List(n), "start") => n.start?.to_string(),
+    (Node::List(n), "spread") => n.spread.to_string(),
+    (Node::ListItem(n), "spread") => n.spread.to_string(),
+    (Node::ListItem(n), "checked") => n.checked?.to_string(),
+    (Node::Link(n), "url") => n.url.clone(),
+    (Node::Link(n), "title") => n.title.clone()?,
+    (Node::Image(n), "url") => n.url.clone(),
+    (Node::Image(n), "title") => n.title.clone()?,
+    (Node::Image(n), "alt") => n.alt.clone(),
+    (Node::Definition(n), "url") => n.url.clone(),
+    (Node::Definition(n), "title") => n.title.clone()?,
+    (Node::Definition(n), "identifier") => n.identifier.clone(),
+    (Node::Definition(n), "label") => n.label.clone()?,
+    (Node::LinkReference(n), "identifier") => n.identifier.clone(),
+    (Node::LinkReference(n), "label") => n.label.clone()?,
+    (Node::ImageReference(n), "identifier") => n.identifier.clone(),
+    (Node::ImageReference(n), "label") => n.label.clone()?,
+    (Node::ImageReference(n), "alt") => n.alt.clone(),
+    (Node::FootnoteReference(n), "identifier") => n.identifier.clone(),
+    (Node::FootnoteReference(n), "label") => n.label.clone()?,
+    (Node::FootnoteDefinition(n), "identifier") => n.identifier.clone(),
+    (Node::FootnoteDefinition(n), "label") => n.label.clone()?,
+    (Node::MdxJsxFlowElement(n), "name") => n.name.clone()?,
+    (Node::MdxJsxTextElement(n), "name") => n.name.clone()?,
+    (Node::MdxJsxFlowElement(n), _) => jsx(&n.attributes, name)?,
+    (Node::MdxJsxTextElement(n), _) => jsx(&n.attributes, name)?,
+    _ => return None,
+  };
+  Some(value)
+}
+
+/// A node with its place in the tree.
+struct Entry<'n> {
+  node: &'n Node,
+  parent: Option<usize>,
+  children: Vec<usize>,
+  /// The entry after the last descendant of the node.
+  end: usize,
+}
+
+/// The tree, flattened in document order so that parents and siblings can be
+/// looked up.
+struct Index<'n> {
+  entries: Vec<Entry<'n>>,
+}
+
+impl<'n> Index<'n> {
+  fn new(tree: &'n Node) -> Index<'n> {
+    let mut entries: Vec<Entry<'n>> = vec![];
+    // the entry of each ancestor of the node being visited
+    let mut stack: Vec<usize> = vec![];
+    walk(tree, |node, context| {
+      stack.truncate(context.ancestors.len());
+      let id = entries.len();
+      let parent = stack.last().copied();
+      if let Some(parent) = parent {
+        entries[parent].children.push(id);
+      }
+      entries.push(Entry {
+        node,
+        parent,
+        children: vec![],
+        end: id + 1,
+      });
+      stack.push(id);
+      Control::Continue
+    });
+    // children come after their parent
+    for id in (0..entries.len()).rev() {
+      if let Some(&last) = entries[id].children.last() {
+        entries[id].end = entries[last].end;
+      }
+    }
+    Index { entries }
+  }
+
+  fn siblings(&self, id: usize) -> &[usize] {
+    match self.entries[id].parent {
+      Some(parent) => &self.entries[parent].children,
+      None => &[],
+    }
+  }
+
+  fn previous_siblings(&self, id: usize) -> &[usize] {
+    let siblings = self.siblings(id);
+    let position = siblings.iter().position(|s| *s == id).unwrap_or(0);
+    &siblings[..position]
+  }
+
+  /// The entries a `:has(selector)` of `id` can match: its descendants, and
+  /// with `+` or `~`, its following siblings and their descendants.
+  fn has_scope(&self, selector: &Selector, id: usize) -> Range<usize> {
+    let siblings = selector
+      .0
+      .iter()
+      .any(|complex| matches!(complex.0[0].0, Combinator::Adjacent | Combinator::Sibling));
+    let end = match self.entries[id].parent {
+      Some(parent) if siblings => self.entries[parent].end,
+      _ => self.entries[id].end,
+    };
+    id + 1..end
+  }
+
+  fn is_ancestor(&self, ancestor: usize, mut id: usize) -> bool {
+    while let Some(parent) = self.entries[id].parent {
+      if parent == ancestor {
+        return true;
+      }
+      id = parent;
+    }
+    false
+  }
+
+  fn matches(&self, selector: &Selector, id: usize, scope: Option<usize>) -> bool {
+    selector
+      .0
+      .iter()
+      .any(|complex| self.matches_complex(&complex.0, complex.0.len() - 1, id, scope))
+  }
+
+  // Match right to left: the last compound against `id`, then each compound
+  // before it against the nodes its combinator allows.
+  fn matches_complex(&self, compounds: &[(Combinator, Compound)], k: usize, id: usize, scope: Option<usize>) -> bool {
+    let (combinator, compound) = &compounds[k];
+    if !self.matches_compound(compound, id) {
+      return false;
+    }
+
+    if k == 0 {
+      return match scope {
+        None => true,
+        Some(scope) => self.related(*combinator, scope, id),
+      };
+    }
+
+    let candidates: Vec<usize> = match combinator {
+      Combinator::Descendant => {
+        let mut ancestors = vec![];
+        let mut current = id;
+        while let Some(parent) = self.entries[current].parent {
+          ancestors.push(parent);
+          current = parent;
+        }
+        ancestors
+      }
+      Combinator::Child => self.entries[id].parent.into_iter().collect(),
+      Combinator::Adjacent => self.previous_siblings(id).last().copied().into_iter().collect(),
+      Combinator::Sibling => self.previous_siblings(id).to_vec(),
+    };
+    candidates
+      .into_iter()
+      .any(|c| self.matches_complex(compounds, k - 1, c, scope))
+  }
+
+  /// Whether `id` is related to `scope` by `combinator`.
+  fn related(&self, combinator: Combinator, scope: usize, id: usize) -> bool {
+    match combinator {
+      Combinator::Descendant => self.is_ancestor(scope, id),
+      Combinator::Child => self.entries[id].parent == Some(scope),
+      Combinator::Adjacent => self.previous_siblings(id).last() == Some(&scope),
+      Combinator::Sibling => self.previous_siblings(id).contains(&scope),
+    }
+  }
+
+  fn matches_compound(&self, compound: &Compound, id: usize) -> bool {
+    let node = self.entries[id].node;
+
+    if let Some(name) = &compound.name {
+      if name != "*" && !name.eq_ignore_ascii_case(type_name(node)) {
+        return false;
+      }
+    }
+
+    let attributes = compound.attributes.iter().all(|a| {
+      let value = match attribute(node, &a.name) {
+        Some(value) => value,
+        None => return false,
+      };
+      match &a.operator {
+        Operator::Exists => true,
+        Operator::Equals(v) => value == *v,
+        Operator::Prefix(v) => value.starts_with(v.as_str()),
+        Operator::Suffix(v) => value.ends_with(v.as_str()),
+        Operator::Contains(v) => value.contains(v.as_str()),
+      }
+    });
+
+    attributes
+      && compound.pseudos.iter().all(|p| match p {
+        Pseudo::FirstChild => self.siblings(id).first() == Some(&id),
+        Pseudo::LastChild => self.siblings(id).last() == Some(&id),
+        Pseudo::Not(selector) => !self.matches(selector, id, None),
+        Pseudo::Has(selector) => self.has_scope(selector, id).any(|other| self.matches(selector, other, Some(id))),
+      })
+  }
+}
+
+impl Selector {
+  /// All nodes in `tree`, including `tree` itself, that match, in document
+  /// order.
+  pub fn select_all<'n>(&self, tree: &'n Node) -> Vec<&'n Node> {
+    let index = Index::new(tree);
+    (0..index.entries.len())
+      .filter(|id| index.matches(self, *id, None))
+      .map(|id| index.entries[id].node)
+      .collect()
+  }
+
+  /// The first node in `tree` that matches.
+  pub fn select<'n>(&self, tree: &'n Node) -> Option<&'n Node> {
+    self.select_all(tree).into_iter().next()
+  }
+}
+
+pub fn select_all<'n>(selector: &str, tree: &'n Node) -> Result<Vec<&'n Node>, String> {
+  Ok(Selector::parse(selector)?.select_all(tree))
+}
+
+pub fn select<'n>(selector: &str, tree: &'n Node) -> Result<Option<&'n Node>, String> {
+  Ok(Selector::parse(selector)?.select(tree))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use markdown::{Constructs, ParseOptions};
+
+  const SOURCE: &str = "# Title
+
+<Tabs>
+<Tab heading=\"Terraform\">
+
+```hcl
+a = 1
+```
+
+</Tab>
+<Tab heading=\"Shell\">
+
+```sh
+echo
+```
+
+</Tab>
+</Tabs>
+
+```hcl
+outside = true
+```
+
+***
+
+## After a break
+
+## Not after a break
+";
+
+  fn tree() -> Node {
+    let options = ParseOptions {
+      constructs: Constructs::mdx(),
+      ..ParseOptions::default()
+    };
+    markdown::to_mdast(SOURCE, &options).unwrap()
+  }
+
+  fn values(selector: &str) -> Vec<String> {
+    let tree = tree();
+    select_all(selector, &tree)
+      .unwrap()
+      .into_iter()
+      .map(|node| match node {
+        Node::Code(code) => code.value.clone(),
+        other => crate::visit::type_name(other).to_owned(),
+      })
+      .collect()
+  }
+
+  #[test]
+  fn descendants_and_attributes() {
+    assert_eq!(values("MdxJsxFlowElement[name=Tabs] Code[lang=hcl]"), vec!["a = 1"]);
+    assert_eq!(values("code[lang=hcl]"), vec!["a = 1", "outside = true"]);
+    assert_eq!(values("Tab > Code"), Vec::<String>::new());
+    assert_eq!(values("[heading^=Sh] > Code"), vec!["echo"]);
+  }
+
+  #[test]
+  fn siblings_and_pseudo_classes() {
+    assert_eq!(values("ThematicBreak + Heading[depth=2]").len(), 1);
+    assert_eq!(values("ThematicBreak ~ Heading").len(), 2);
+    assert_eq!(values("Heading:first-child"), vec!["Heading"]);
+    assert_eq!(values("MdxJsxFlowElement:has(> Code)").len(), 2);
+    assert_eq!(values("Code:has(~ ThematicBreak)"), vec!["outside = true"]);
+    assert_eq!(values("Heading:has(+ Heading)").len(), 1);
+    assert_eq!(values("[heading=Terraform]:has(~ Code)").len(), 0);
+    assert_eq!(values("[heading=Terraform]:has(+ * > Code)").len(), 1);
+    assert_eq!(values("Code:not([lang=hcl])"), vec!["echo"]);
+    assert_eq!(values("Root > Code, MdxJsxFlowElement Code[lang=sh]"), vec!["echo", "outside = true"]);
+  }
+
+  #[test]
+  fn reports_parse_errors() {
+    assert!(Selector::parse("Code[lang").is_err());
+    assert!(Selector::parse("Code:nth-child(2)").is_err());
+    assert!(Selector::parse("Code >").is_err());
+  }
+}
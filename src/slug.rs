@@ -0,0 +1,199 @@
+//! Anchor ids for headings, compatible with the ones GitHub generates.
+//!
+//! `## Install {#setup}` gets the explicit id `setup`, and the `{#setup}` is
+//! removed from the heading.
+
+use crate::visit::{walk, walk_mut, Control};
+use markdown::mdast::{Heading, Node, Text};
+use std::collections::HashMap;
+
+/// The GitHub slug of `text`: lowercase, punctuation removed and spaces
+/// turned into dashes.
+pub fn slug(text: &str) -> String {
+  text
+    .to_lowercase()
+    .chars()
+    .filter(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_'))
+    .map(|c| if c == ' ' { '-' } else { c })
+    .collect()
+}
+
+/// Makes slugs unique within a document by adding `-1`, `-2`, ... to
+/// repeated ones.
+#[derive(Debug, Default)]
+pub struct Slugger {
+  seen: HashMap<String, usize>,
+}
+
+impl Slugger {
+  pub fn new() -> Slugger {
+    Slugger::default()
+  }
+
+  pub fn slug(&mut self, text: &str) -> String {
+    let base = slug(text);
+    let mut id = base.clone();
+    while self.seen.contains_key(&id) {
+      let count = self.seen.get_mut(&base).unwrap();
+      *count += 1;
+      id = format!("{}-{}", base, count);
+    }
+    self.seen.insert(id.clone(), 0);
+    id
+  }
+
+  /// Reserve an explicit id, so generated ones do not collide with it.
+  pub fn reserve(&mut self, id: &str) {
+    self.seen.entry(id.to_owned()).or_insert(0);
+  }
+}
+
+/// The text of `node` as GitHub sees it when making a slug: the values of
+/// text, inline code and inline math, without markup.
+pub fn text(node: &Node) -> String {
+  let mut value = String::new();
+  walk(node, |node, _| match node {
+    Node::Text(n) => {
+      value.push_str(&n.value);
+      Control::Continue
+    }
+    Node::InlineCode(n) => {
+      value.push_str(&n.value);
+      Control::Continue
+    }
+    Node::InlineMath(n) => {
+      value.push_str(&n.value);
+      Control::Continue
+    }
+    Node::Html(_) | Node::Image(_) | Node::ImageReference(_) | Node::MdxTextExpression(_) => Control::Skip,
+    _ => Control::Continue,
+  });
+  value
+}
+
+/// Remove a trailing `{#id}` from `heading` and return the id. In MDX,
+/// `{#id}` is an expression rather than text.
+pub fn take_custom_id(heading: &mut Heading) -> Option<String> {
+  let id = match heading.children.last_mut() {
+    Some(Node::Text(last)) => {
+      let trimmed = last.value.trim_end();
+      let open = trimmed.rfind("{#")?;
+      let id = custom_id(trimmed[open + 2..].strip_suffix('}')?)?;
+      truncate(last, open);
+      id
+    }
+    Some(Node::MdxTextExpression(last)) => {
+      let id = custom_id(last.value.trim().strip_prefix('#')?)?;
+      heading.children.pop();
+      id
+    }
+    _ => return None,
+  };
+
+  // the whitespace before `{#id}`
+  if let Some(Node::Text(last)) = heading.children.last_mut() {
+    truncate(last, last.value.trim_end().len());
+    if last.value.is_empty() {
+      heading.children.pop();
+    }
+  }
+  Some(id)
+}
+
+fn truncate(text: &mut Text, len: usize) {
+  let removed = text.value.len() - len;
+  text.value.truncate(len);
+  if let Some(position) = &mut text.position {
+    // what is removed is on the last line of the text, and columns count bytes
+    position.end.offset -= removed;
+    position.end.column -= removed;
+  }
+}
+
+fn custom_id(id: &str) -> Option<String> {
+  if id.is_empty() || id.contains(|c: char| c.is_whitespace() || c == '{' || c == '}') {
+    return None;
+  }
+  Some(id.to_owned())
+}
+
+/// Assign an id to every heading in `tree`, removing `{#id}` syntax from
+/// them; returns the ids in document order.
+pub fn heading_ids(tree: &mut Node) -> Vec<String> {
+  // explicit ids are collected first so that generated ones never take them
+  let mut custom = vec![];
+  walk_mut(tree, |node, _| {
+    if let Node::Heading(heading) = node {
+      custom.push(take_custom_id(heading));
+    }
+    Control::Continue
+  });
+
+  let mut slugger = Slugger::new();
+  for id in custom.iter().flatten() {
+    slugger.reserve(id);
+  }
+
+  let mut custom = custom.into_iter();
+  let mut ids = vec![];
+  walk(tree, |node, _| {
+    if let Node::Heading(_) = node {
+      ids.push(match custom.next().flatten() {
+        Some(id) => id,
+        None => slugger.slug(&text(node)),
+      });
+      return Control::Skip;
+    }
+    Control::Continue
+  });
+  ids
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn slugs_like_github() {
+    assert_eq!(slug("Hello, World!"), "hello-world");
+    assert_eq!(slug("  a  b "), "--a--b-");
+    assert_eq!(slug("Ünïcödé & snake_case"), "ünïcödé--snake_case");
+
+    let mut slugger = Slugger::new();
+    let ids = ["a", "a", "a-1", "a"].map(|t| slugger.slug(t));
+    assert_eq!(ids, ["a", "a-1", "a-1-1", "a-2"]);
+  }
+
+  #[test]
+  fn assigns_ids_to_headings() {
+    let source = "# Intro\n\n## `code` *and* text {#custom}\n\n## Intro\n\n# custom\n";
+    let mut tree = markdown::to_mdast(source, &markdown::ParseOptions::default()).unwrap();
+    let ids = heading_ids(&mut tree);
+    assert_eq!(ids, vec!["intro", "custom", "intro-1", "custom-1"]);
+
+    let heading = &tree.children().unwrap()[1];
+    let last = heading.children().unwrap().last().unwrap();
+    match last {
+      Node::Text(text) => {
+        assert_eq!(text.value, " text");
+        let position = text.position.as_ref().unwrap();
+        assert_eq!(&source[position.start.offset..position.end.offset], " text");
+      }
+      other => panic!("expected text, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn takes_ids_from_mdx_expressions() {
+    let source = "## Install *now* {#setup}
+";
+    let mut tree = markdown::to_mdast(source, &markdown::ParseOptions::mdx()).unwrap();
+    assert_eq!(heading_ids(&mut tree), vec!["setup"]);
+
+    let heading = &tree.children().unwrap()[0];
+    match heading.children().unwrap().last().unwrap() {
+      Node::Emphasis(_) => {}
+      other => panic!("expected emphasis, got {:?}", other),
+    }
+  }
+}
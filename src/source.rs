@@ -0,0 +1,301 @@
+use markdown::unist::Position;
+
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+// Files are read as bytes and normalized into a UTF-8 string with `\n` line
+// endings before anything else sees them. Everything downstream works on that
+// string; `Source::original_offset` maps its byte offsets back to the bytes on
+// disk. Lines are not affected by normalization, and columns keep counting
+// bytes of the normalized line.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Normalization {
+  /// A UTF-8 byte order mark was removed.
+  Utf8Bom,
+  /// The file was decoded from UTF-16, little endian.
+  Utf16Le,
+  /// The file was decoded from UTF-16, big endian.
+  Utf16Be,
+  /// `\r\n` line endings were turned into `\n`.
+  Crlf,
+}
+
+#[derive(Debug)]
+pub struct Source {
+  pub text: String,
+  pub normalizations: Vec<Normalization>,
+  /// Offset on disk of every byte in `text`, plus one for the end; only
+  /// needed when the mapping is not a fixed shift.
+  offsets: Option<Vec<usize>>,
+  /// Length of a removed UTF-8 byte order mark.
+  shift: usize,
+}
+
+impl Source {
+  pub fn read(path: &Path) -> Result<Source, String> {
+    let bytes = fs::read(path).map_err(|e| format!("{}: could not read file: {}", path.display(), e))?;
+    Source::decode(&bytes).map_err(|e| format!("{}: {}", path.display(), e))
+  }
+
+  pub fn decode(bytes: &[u8]) -> Result<Source, String> {
+    let mut normalizations = vec![];
+
+    // (character, offset on disk)
+    let chars: Vec<(char, usize)> = match bytes {
+      [0xEF, 0xBB, 0xBF, rest @ ..] => {
+        normalizations.push(Normalization::Utf8Bom);
+        utf8(rest, 3)?
+      }
+      [0xFF, 0xFE, rest @ ..] => {
+        normalizations.push(Normalization::Utf16Le);
+        utf16(rest, 2, u16::from_le_bytes)?
+      }
+      [0xFE, 0xFF, rest @ ..] => {
+        normalizations.push(Normalization::Utf16Be);
+        utf16(rest, 2, u16::from_be_bytes)?
+      }
+      // Without a byte order mark, UTF-16 shows itself through the zero byte
+      // of an ASCII first character.
+      [a, 0, ..] if *a != 0 && bytes.len().is_multiple_of(2) => {
+        normalizations.push(Normalization::Utf16Le);
+        utf16(bytes, 0, u16::from_le_bytes)?
+      }
+      [0, b, ..] if *b != 0 && bytes.len().is_multiple_of(2) => {
+        normalizations.push(Normalization::Utf16Be);
+        utf16(bytes, 0, u16::from_be_bytes)?
+      }
+      _ => {
+        // The common case needs no table.
+        let text = std::str::from_utf8(bytes).map_err(|e| invalid_utf8(&e, 0))?;
+        if !text.contains("\r\n") {
+          return Ok(Source {
+            text: text.to_owned(),
+            normalizations,
+            offsets: None,
+            shift: 0,
+          });
+        }
+        utf8(bytes, 0)?
+      }
+    };
+
+    let mut text = String::with_capacity(chars.len());
+    let mut offsets = Vec::with_capacity(chars.len() + 1);
+    let mut crlf = false;
+    for (index, &(c, offset)) in chars.iter().enumerate() {
+      if c == '\r' && matches!(chars.get(index + 1), Some(('\n', _))) {
+        crlf = true;
+        continue;
+      }
+      // The `\n` of a `\r\n` maps to the `\r`, so line ends stay before both.
+      let offset = if c == '\n' && index > 0 && chars[index - 1].0 == '\r' {
+        chars[index - 1].1
+      } else {
+        offset
+      };
+      text.push(c);
+      offsets.extend(std::iter::repeat_n(offset, c.len_utf8()));
+    }
+    offsets.push(bytes.len());
+    if crlf {
+      normalizations.push(Normalization::Crlf);
+    }
+
+    let utf16 = normalizations
+      .iter()
+      .any(|n| matches!(n, Normalization::Utf16Le | Normalization::Utf16Be));
+    if !utf16 && !crlf {
+      return Ok(Source {
+        text,
+        normalizations,
+        offsets: None,
+        shift: 3,
+      });
+    }
+
+    Ok(Source {
+      text,
+      normalizations,
+      offsets: Some(offsets),
+      shift: 0,
+    })
+  }
+
+  /// Whether offsets into `text` are offsets on disk.
+  pub fn is_identity(&self) -> bool {
+    self.offsets.is_none() && self.shift == 0
+  }
+
+  /// Map a byte offset into `text` to a byte offset in the file on disk.
+  pub fn original_offset(&self, offset: usize) -> usize {
+    match &self.offsets {
+      Some(offsets) => offsets[offset.min(offsets.len() - 1)],
+      None => offset + self.shift,
+    }
+  }
+
+  pub fn remap_position(&self, position: &mut Position) {
+    position.start.offset = self.original_offset(position.start.offset);
+    position.end.offset = self.original_offset(position.end.offset);
+  }
+
+  /// Map a byte offset in the file on disk back to a byte offset into `text`;
+  /// the inverse of `original_offset`.
+  pub fn text_offset(&self, original: usize) -> usize {
+    match &self.offsets {
+      Some(offsets) => offsets.partition_point(|&o| o < original).min(self.text.len()),
+      None => original.saturating_sub(self.shift).min(self.text.len()),
+    }
+  }
+
+  /// `text` encoded like the file on disk, to be written at offset `at` of
+  /// `self.text`: in UTF-16 if the file was, and with each line ending as it
+  /// was on that line on disk. Lines past the end of the file end like its
+  /// last line. A byte order mark is not included.
+  pub fn encode(&self, text: &str, at: usize) -> Vec<u8> {
+    let endings = self
+      .text
+      .match_indices('\n')
+      .map(|(index, _)| self.is_crlf(index))
+      .collect::<Vec<bool>>();
+    let first = self.text[..at.min(self.text.len())].matches('\n').count();
+
+    let mut encoded = String::with_capacity(text.len());
+    for (index, line) in text.split_inclusive('\n').enumerate() {
+      match line.strip_suffix('\n') {
+        Some(line) => {
+          encoded.push_str(line);
+          let crlf = endings.get(first + index).or(endings.last()).copied().unwrap_or_default();
+          encoded.push_str(if crlf { "\r\n" } else { "\n" });
+        }
+        None => encoded.push_str(line),
+      }
+    }
+    if self.normalizations.contains(&Normalization::Utf16Le) {
+      encoded.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    } else if self.normalizations.contains(&Normalization::Utf16Be) {
+      encoded.encode_utf16().flat_map(u16::to_be_bytes).collect()
+    } else {
+      encoded.into_bytes()
+    }
+  }
+
+  /// Whether the `\n` at `index` in `text` was a `\r\n` on disk.
+  fn is_crlf(&self, index: usize) -> bool {
+    let offsets = match &self.offsets {
+      Some(offsets) => offsets,
+      None => return false,
+    };
+    let unit = if self.normalizations.contains(&Normalization::Utf16Le)
+      || self.normalizations.contains(&Normalization::Utf16Be)
+    {
+      2
+    } else {
+      1
+    };
+    // the `\n` of a `\r\n` maps to the `\r`, so it spans both
+    offsets[index + 1] - offsets[index] == 2 * unit
+  }
+
+  /// The text `position` spans, where `position` is on disk, like the
+  /// positions in a parsed document.
+  pub fn slice(&self, position: &Position) -> &str {
+    &self.text[self.text_offset(position.start.offset)..self.text_offset(position.end.offset)]
+  }
+}
+
+fn utf8(bytes: &[u8], base: usize) -> Result<Vec<(char, usize)>, String> {
+  let text = std::str::from_utf8(bytes).map_err(|e| invalid_utf8(&e, base))?;
+  Ok(text.char_indices().map(|(i, c)| (c, i + base)).collect())
+}
+
+fn invalid_utf8(error: &std::str::Utf8Error, base: usize) -> String {
+  format!("invalid UTF-8 at byte {}", error.valid_up_to() + base)
+}
+
+fn utf16(bytes: &[u8], base: usize, unit: fn([u8; 2]) -> u16) -> Result<Vec<(char, usize)>, String> {
+  if !bytes.len().is_multiple_of(2) {
+    return Err("invalid UTF-16: odd number of bytes".to_owned());
+  }
+  let units = bytes
+    .chunks(2)
+    .map(|pair| unit([pair[0], pair[1]]))
+    .collect::<Vec<u16>>();
+
+  let mut chars = vec![];
+  let mut index = 0;
+  for c in char::decode_utf16(units.iter().copied()) {
+    let c = c.map_err(|_| format!("invalid UTF-16 at byte {}", base + index * 2))?;
+    chars.push((c, base + index * 2));
+    index += c.len_utf16();
+  }
+  Ok(chars)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn plain_utf8_is_untouched() {
+    let source = Source::decode(b"# a\n").unwrap();
+    assert!(source.is_identity());
+    assert!(source.normalizations.is_empty());
+  }
+
+  #[test]
+  fn maps_bom_and_crlf_back_to_disk() {
+    let bytes = b"\xEF\xBB\xBF# a\r\n\r\nb\r\n";
+    let source = Source::decode(bytes).unwrap();
+    assert_eq!(source.text, "# a\n\nb\n");
+    assert_eq!(source.normalizations, vec![Normalization::Utf8Bom, Normalization::Crlf]);
+
+    let b = source.text.find('b').unwrap();
+    assert_eq!(bytes[source.original_offset(b)], b'b');
+    assert_eq!(bytes[source.original_offset(b + 1)], b'\r');
+    assert_eq!(source.original_offset(source.text.len()), bytes.len());
+    assert_eq!(source.text_offset(source.original_offset(b)), b);
+    assert_eq!(source.text_offset(bytes.len()), source.text.len());
+  }
+
+  #[test]
+  fn encodes_line_endings_per_line() {
+    let bytes = b"a\r\nb\nc\r\n";
+    let source = Source::decode(bytes).unwrap();
+    assert_eq!(source.encode(&source.text, 0), bytes);
+    assert_eq!(source.encode("B\nC\n", 2), b"B\nC\r\n");
+    assert_eq!(source.encode("c\nd\ne\n", 4), b"c\r\nd\r\ne\r\n");
+
+    let mut le = vec![0xFF, 0xFE];
+    for unit in "a\nb\r\n".encode_utf16() {
+      le.extend(unit.to_le_bytes());
+    }
+    let source = Source::decode(&le).unwrap();
+    assert_eq!(source.encode(&source.text, 0), le[2..]);
+  }
+
+  #[test]
+  fn decodes_utf16() {
+    let text = "---\ntitle: é\n---\r\n# 𝒜\n";
+    let mut le = vec![0xFF, 0xFE];
+    let mut be = vec![];
+    for unit in text.encode_utf16() {
+      le.extend(unit.to_le_bytes());
+      be.extend(unit.to_be_bytes());
+    }
+
+    let source = Source::decode(&le).unwrap();
+    assert_eq!(source.text, "---\ntitle: é\n---\n# 𝒜\n");
+    assert_eq!(source.normalizations, vec![Normalization::Utf16Le, Normalization::Crlf]);
+    let heading = source.text.find('#').unwrap();
+    assert_eq!(le[source.original_offset(heading)], b'#');
+
+    let source = Source::decode(&be).unwrap();
+    assert_eq!(source.normalizations, vec![Normalization::Utf16Be, Normalization::Crlf]);
+    let heading = source.text.find('#').unwrap();
+    assert_eq!(be[source.original_offset(heading) + 1], b'#');
+  }
+}
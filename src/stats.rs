@@ -0,0 +1,131 @@
+//! Size and complexity of documents.
+
+use crate::section;
+use crate::text::{to_text, TextOptions};
+use crate::visit::{walk, Control};
+use markdown::mdast::Node;
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Words read per minute, for `reading_time`.
+pub const WORDS_PER_MINUTE: usize = 200;
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+  /// Number of documents counted.
+  pub files: usize,
+  /// Words in the text, leaving out code.
+  pub words: usize,
+  /// Characters in the text, leaving out code, with runs of whitespace
+  /// counted as one.
+  pub characters: usize,
+  /// Estimated minutes to read the text, rounded up.
+  pub reading_time: usize,
+  /// Fenced and indented code blocks by language; `none` when there is none.
+  pub code_blocks: BTreeMap<String, usize>,
+  /// Headings by depth: `headings[0]` counts `#`, `headings[5]` `######`.
+  pub headings: [usize; 6],
+  /// Links, including references to definitions.
+  pub links: usize,
+  /// Images, including references to definitions.
+  pub images: usize,
+  /// JSX elements by name; `<>` fragments are counted as `Fragment`.
+  pub jsx: BTreeMap<String, usize>,
+}
+
+impl Stats {
+  pub fn of(tree: &Node) -> Stats {
+    let text = to_text(
+      tree,
+      &TextOptions {
+        block_separator: " ".to_owned(),
+        include_jsx: true,
+        skip: vec!["Code".to_owned(), "InlineCode".to_owned()],
+        ..TextOptions::default()
+      },
+    );
+    let mut stats = Stats {
+      files: 1,
+      words: text.split_whitespace().count(),
+      characters: text.chars().count(),
+      ..Stats::default()
+    };
+
+    walk(tree, |node, _| {
+      match node {
+        Node::Code(code) => {
+          let lang = code.lang.clone().unwrap_or_else(|| "none".to_owned());
+          *stats.code_blocks.entry(lang).or_insert(0) += 1;
+        }
+        Node::Heading(heading) => stats.headings[usize::from(heading.depth.clamp(1, 6)) - 1] += 1,
+        Node::Link(_) | Node::LinkReference(_) => stats.links += 1,
+        Node::Image(_) | Node::ImageReference(_) => stats.images += 1,
+        Node::MdxJsxFlowElement(element) if !section::is_section(node) => stats.add_jsx(element.name.as_deref()),
+        Node::MdxJsxTextElement(element) => stats.add_jsx(element.name.as_deref()),
+        _ => {}
+      }
+      Control::Continue
+    });
+    stats.reading_time = reading_time(stats.words);
+    stats
+  }
+
+  fn add_jsx(&mut self, name: Option<&str>) {
+    *self.jsx.entry(name.unwrap_or("Fragment").to_owned()).or_insert(0) += 1;
+  }
+
+  /// Add the counts of `other`, e.g. to summarize a corpus.
+  pub fn add(&mut self, other: &Stats) {
+    self.files += other.files;
+    self.words += other.words;
+    self.characters += other.characters;
+    for (lang, count) in &other.code_blocks {
+      *self.code_blocks.entry(lang.clone()).or_insert(0) += count;
+    }
+    for (total, count) in self.headings.iter_mut().zip(other.headings) {
+      *total += count;
+    }
+    self.links += other.links;
+    self.images += other.images;
+    for (name, count) in &other.jsx {
+      *self.jsx.entry(name.clone()).or_insert(0) += count;
+    }
+    self.reading_time = reading_time(self.words);
+  }
+}
+
+fn reading_time(words: usize) -> usize {
+  words.div_ceil(WORDS_PER_MINUTE)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn counts_nodes() {
+    let source = "# One two\n\n## Three\n\nfour `five` [six](a) ![seven](b.png)\n\n```rust\nfn main() {}\n```\n\n```\nx\n```\n\n<Note>eight</Note>\n\n<></>\n";
+    let options = markdown::ParseOptions {
+      constructs: markdown::Constructs::mdx(),
+      ..markdown::ParseOptions::default()
+    };
+    let tree = markdown::to_mdast(source, &options).unwrap();
+    let stats = Stats::of(&tree);
+
+    assert_eq!(stats.words, 6);
+    assert_eq!(stats.characters, "One two Three four six eight".len());
+    assert_eq!(stats.reading_time, 1);
+    assert_eq!(stats.code_blocks.get("rust"), Some(&1));
+    assert_eq!(stats.code_blocks.get("none"), Some(&1));
+    assert_eq!(stats.headings, [1, 1, 0, 0, 0, 0]);
+    assert_eq!((stats.links, stats.images), (1, 1));
+    assert_eq!(stats.jsx.get("Note"), Some(&1));
+    assert_eq!(stats.jsx.get("Fragment"), Some(&1));
+
+    let mut total = Stats::default();
+    total.add(&stats);
+    total.add(&stats);
+    assert_eq!((total.files, total.words, total.code_blocks["rust"]), (2, 12, 2));
+  }
+}
@@ -0,0 +1,922 @@
+//! Write `markdown::mdast::Node` trees back to markdown and MDX.
+//!
+//! Parsing the output again gives the same tree, apart from positions: text
+//! is escaped where it would otherwise be markup, fences are made longer
+//! than the code they hold, and so on. How the markup looks is up to
+//! `StringifyOptions`.
+
+use markdown::mdast::{AlignKind, AttributeContent, AttributeValue, List, Node, ReferenceKind, Table};
+use markdown::Constructs;
+
+use serde::Deserialize;
+use std::cell::Cell;
+
+/// Markers to write markup with, and how to lay out headings and prose.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct StringifyOptions {
+  /// Bullet of unordered list items: `-`, `*` or `+`.
+  pub bullet: char,
+  /// Emphasis marker: `*` or `_`.
+  pub emphasis: char,
+  /// Strong marker: `*` or `_`.
+  pub strong: char,
+  /// Code fence marker: `` ` `` or `~`.
+  pub fence: char,
+  /// Thematic break marker: `-`, `*` or `_`.
+  pub rule: char,
+  /// Style of level 1 and 2 headings; deeper ones are always ATX.
+  pub heading: HeadingStyle,
+  /// How the lines of paragraphs are broken.
+  pub prose_wrap: ProseWrap,
+  /// Line width for `prose_wrap: always`, including the indent of block
+  /// quotes and list items.
+  pub width: usize,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HeadingStyle {
+  /// `# Title`
+  #[default]
+  Atx,
+  /// `Title` underlined with `=` or `-`.
+  Setext,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ProseWrap {
+  /// Keep line breaks where they are.
+  #[default]
+  Preserve,
+  /// Break lines at `width`.
+  Always,
+  /// Put every paragraph on one line, up to hard breaks.
+  Never,
+}
+
+impl Default for StringifyOptions {
+  fn default() -> StringifyOptions {
+    StringifyOptions {
+      bullet: '-',
+      emphasis: '*',
+      strong: '*',
+      fence: '`',
+      rule: '-',
+      heading: HeadingStyle::Atx,
+      prose_wrap: ProseWrap::Preserve,
+      width: 80,
+    }
+  }
+}
+
+impl StringifyOptions {
+  pub fn validate(&self) -> Result<(), String> {
+    let check = |name: &str, value: char, allowed: &[char]| {
+      if allowed.contains(&value) {
+        Ok(())
+      } else {
+        let allowed = allowed.iter().map(|c| format!("`{}`", c)).collect::<Vec<String>>();
+        Err(format!("invalid {} `{}`, expected one of: {}", name, value, allowed.join(", ")))
+      }
+    };
+    check("bullet", self.bullet, &['-', '*', '+'])?;
+    check("emphasis", self.emphasis, &['*', '_'])?;
+    check("strong", self.strong, &['*', '_'])?;
+    check("fence", self.fence, &['`', '~'])?;
+    check("rule", self.rule, &['-', '*', '_'])?;
+    if self.width == 0 {
+      return Err("invalid width `0`, expected a positive number".to_owned());
+    }
+    Ok(())
+  }
+}
+
+/// `tree` as markdown, ending in a line ending unless it is empty.
+/// `constructs` are those the output will be parsed with, which decide what
+/// needs escaping.
+pub fn to_markdown(tree: &Node, constructs: &Constructs, options: &StringifyOptions) -> String {
+  let writer = Writer {
+    constructs,
+    options,
+    wrapping: Cell::new(false),
+    indent: Cell::new(0),
+  };
+  let mut value = writer.block(tree, false);
+  let trimmed = value.trim_end_matches('\n').len();
+  value.truncate(trimmed);
+  if !value.is_empty() {
+    value.push('\n');
+  }
+  value
+}
+
+struct Writer<'a> {
+  constructs: &'a Constructs,
+  options: &'a StringifyOptions,
+  /// Whether spaces and line endings in text are written as `BREAK`s, while
+  /// in a paragraph that is rewrapped.
+  wrapping: Cell<bool>,
+  /// Width of the prefixes of the current block, such as `> `.
+  indent: Cell<usize>,
+}
+
+/// Marks a space or line ending in a paragraph, where `fill` may break lines.
+const BREAK: char = '\0';
+
+impl<'a> Writer<'a> {
+  /// A flow node; `alternate` asks lists for the other marker, so that they
+  /// do not join the list before them.
+  fn block(&self, node: &Node, alternate: bool) -> String {
+    match node {
+      Node::Root(n) => self.flow(&n.children, false),
+      Node::Paragraph(n) => {
+        if self.options.prose_wrap == ProseWrap::Preserve {
+          return self.phrasing(&n.children);
+        }
+        self.wrapping.set(true);
+        let text = self.phrasing(&n.children);
+        self.wrapping.set(false);
+        fill(&text, self.options.prose_wrap, self.options.width.saturating_sub(self.indent.get()))
+      }
+      Node::Heading(n) => {
+        let mut text = self.phrasing(&n.children);
+        let setext = text.contains('\n') || self.options.heading == HeadingStyle::Setext;
+        if n.depth <= 2 && setext && !text.is_empty() {
+          let width = text.split('\n').map(|line| line.chars().count()).max().unwrap_or(0);
+          let underline = if n.depth == 1 { "=" } else { "-" };
+          return format!("{}\n{}", text, underline.repeat(width.max(3)));
+        }
+        if text.contains('\n') {
+          text = text.replace("\\\n", " ").replace('\n', " ");
+        }
+        // a final `#` would be taken for a closing sequence
+        if text.ends_with('#') && !text.ends_with("\\#") {
+          text.insert(text.len() - 1, '\\');
+        }
+        let hashes = "#".repeat(n.depth.into());
+        if text.is_empty() {
+          hashes
+        } else {
+          format!("{} {}", hashes, text)
+        }
+      }
+      Node::ThematicBreak(_) => self.options.rule.to_string().repeat(3),
+      Node::BlockQuote(n) => indent(&self.nested(2, || self.flow(&n.children, false)), "> ", "> "),
+      Node::List(n) => self.list(n, alternate),
+      Node::Code(n) => {
+        let info = [n.lang.as_deref(), n.meta.as_deref()]
+          .iter()
+          .flatten()
+          .copied()
+          .collect::<Vec<&str>>()
+          .join(" ");
+        // backticks cannot fence an info string that has them
+        let marker = if self.options.fence == '`' && info.contains('`') {
+          '~'
+        } else {
+          self.options.fence
+        };
+        fenced(marker, 3, &info, &n.value)
+      }
+      Node::Math(n) => fenced('$', 2, n.meta.as_deref().unwrap_or_default(), &n.value),
+      Node::Html(n) => n.value.clone(),
+      Node::Definition(n) => {
+        let label = n.label.as_deref().unwrap_or(&n.identifier);
+        format!("[{}]: {}{}", label, destination(&n.url), title(n.title.as_deref()))
+      }
+      Node::FootnoteDefinition(n) => {
+        let label = n.label.as_deref().unwrap_or(&n.identifier);
+        let content = self.nested(4, || self.flow(&n.children, false));
+        if content.is_empty() {
+          format!("[^{}]:", label)
+        } else {
+          indent(&content, &format!("[^{}]: ", label), "    ")
+        }
+      }
+      Node::Table(n) => self.table(n),
+      Node::MdxjsEsm(n) => n.value.clone(),
+      Node::MdxFlowExpression(n) => format!("{{{}}}", n.value),
+      Node::MdxJsxFlowElement(n) => {
+        let open = jsx_open(n.name.as_deref(), &n.attributes);
+        let close = jsx_close(n.name.as_deref());
+        if n.children.is_empty() {
+          self_closing(&open, n.name.is_some(), &close)
+        } else {
+          let content = self.nested(2, || self.flow(&n.children, false));
+          format!("{}\n{}\n{}", open, indent(&content, "  ", "  "), close)
+        }
+      }
+      Node::Yaml(n) => format!("---\n{}\n---", n.value),
+      Node::Toml(n) => format!("+++\n{}\n+++", n.value),
+      _ => self.phrasing(std::slice::from_ref(node)),
+    }
+  }
+
+  /// Flow nodes, with a blank line between them unless `tight`.
+  fn flow(&self, nodes: &[Node], tight: bool) -> String {
+    let mut blocks = vec![];
+    let mut previous: Option<&Node> = None;
+    // whether the last list used the other marker
+    let mut alternated = false;
+    for node in nodes {
+      let alternate = match (previous, node) {
+        (Some(Node::List(a)), Node::List(b)) if a.ordered == b.ordered => !alternated,
+        _ => false,
+      };
+      if let Node::List(_) = node {
+        alternated = alternate;
+      }
+      blocks.push(self.block(node, alternate));
+      previous = Some(node);
+    }
+    blocks.join(if tight { "\n" } else { "\n\n" })
+  }
+
+  /// `block` run with the indent of its prefix, `by` characters, added.
+  fn nested(&self, by: usize, block: impl FnOnce() -> String) -> String {
+    self.indent.set(self.indent.get() + by);
+    let value = block();
+    self.indent.set(self.indent.get() - by);
+    value
+  }
+
+  fn list(&self, list: &List, alternate: bool) -> String {
+    let mut items = vec![];
+    for (index, item) in list.children.iter().enumerate() {
+      let marker = if list.ordered {
+        let number = list.start.unwrap_or(1) as usize + index;
+        format!("{}{}", number, if alternate { ')' } else { '.' })
+      } else if alternate {
+        (if self.options.bullet == '-' { '*' } else { '-' }).to_string()
+      } else {
+        self.options.bullet.to_string()
+      };
+
+      let (mut content, checked) = match item {
+        Node::ListItem(item) => (
+          self.nested(marker.len() + 1, || self.flow(&item.children, !item.spread)),
+          item.checked,
+        ),
+        other => (self.block(other, false), None),
+      };
+      match checked {
+        Some(true) => content.insert_str(0, "[x] "),
+        Some(false) => content.insert_str(0, "[ ] "),
+        None => {}
+      }
+      if content.is_empty() {
+        items.push(marker);
+      } else {
+        let rest = " ".repeat(marker.len() + 1);
+        items.push(indent(&content, &format!("{} ", marker), &rest));
+      }
+    }
+    items.join(if list.spread { "\n\n" } else { "\n" })
+  }
+
+  fn table(&self, table: &Table) -> String {
+    let rows = table
+      .children
+      .iter()
+      .map(|row| match row {
+        Node::TableRow(row) => row
+          .children
+          .iter()
+          .map(|cell| match cell {
+            Node::TableCell(cell) => self.phrasing(&cell.children),
+            other => self.phrasing(std::slice::from_ref(other)),
+          })
+          .collect::<Vec<String>>(),
+        _ => vec![],
+      })
+      .collect::<Vec<Vec<String>>>();
+    let columns = rows.iter().map(Vec::len).chain([table.align.len()]).max().unwrap_or(0);
+    let align = |column: usize| table.align.get(column).copied().unwrap_or(AlignKind::None);
+    let widths = (0..columns)
+      .map(|column| {
+        rows
+          .iter()
+          .filter_map(|row| row.get(column))
+          .map(|cell| cell.chars().count())
+          .chain([3])
+          .max()
+          .unwrap_or(3)
+      })
+      .collect::<Vec<usize>>();
+
+    let line = |cells: Vec<String>| format!("| {} |", cells.join(" | "));
+    let mut lines = vec![];
+    for (index, row) in rows.iter().enumerate() {
+      let cells = (0..columns)
+        .map(|column| {
+          let cell = row.get(column).map(String::as_str).unwrap_or_default();
+          let space = widths[column] - cell.chars().count();
+          match align(column) {
+            AlignKind::Right => format!("{}{}", " ".repeat(space), cell),
+            AlignKind::Center => format!("{}{}{}", " ".repeat(space / 2), cell, " ".repeat(space - space / 2)),
+            _ => format!("{}{}", cell, " ".repeat(space)),
+          }
+        })
+        .collect();
+      lines.push(line(cells));
+      if index == 0 {
+        let delimiters = (0..columns)
+          .map(|column| {
+            let width = widths[column];
+            match align(column) {
+              AlignKind::Left => format!(":{}", "-".repeat(width - 1)),
+              AlignKind::Right => format!("{}:", "-".repeat(width - 1)),
+              AlignKind::Center => format!(":{}:", "-".repeat(width - 2)),
+              AlignKind::None => "-".repeat(width),
+            }
+          })
+          .collect();
+        lines.push(line(delimiters));
+      }
+    }
+    lines.join("\n")
+  }
+
+  fn phrasing(&self, nodes: &[Node]) -> String {
+    let mut out = String::new();
+    for node in nodes {
+      self.inline(node, &mut out);
+    }
+    out
+  }
+
+  fn inline(&self, node: &Node, out: &mut String) {
+    match node {
+      Node::Text(n) => self.text(&n.value, out),
+      Node::Emphasis(n) => {
+        // `_` does not work inside words
+        let marker = if self.options.emphasis == '_' && out.ends_with(char::is_alphanumeric) {
+          '*'
+        } else {
+          self.options.emphasis
+        };
+        out.push(marker);
+        self.children(&n.children, out);
+        out.push(marker);
+      }
+      Node::Strong(n) => {
+        let marker = if self.options.strong == '_' && out.ends_with(char::is_alphanumeric) {
+          "**"
+        } else if self.options.strong == '_' {
+          "__"
+        } else {
+          "**"
+        };
+        out.push_str(marker);
+        self.children(&n.children, out);
+        out.push_str(marker);
+      }
+      Node::Delete(n) => {
+        out.push_str("~~");
+        self.children(&n.children, out);
+        out.push_str("~~");
+      }
+      Node::InlineCode(n) => out.push_str(&code_span('`', &n.value)),
+      Node::InlineMath(n) => out.push_str(&code_span('$', &n.value)),
+      Node::Break(_) => out.push_str("\\\n"),
+      Node::Html(n) => out.push_str(&n.value),
+      Node::Link(n) => {
+        escape_bang(out);
+        let text = match n.children.as_slice() {
+          [Node::Text(t)] => Some(t.value.as_str()),
+          _ => None,
+        };
+        let autolink = n.title.is_none()
+          && text.is_some_and(|t| t == n.url || format!("mailto:{}", t) == n.url)
+          && n.url.contains(':')
+          && !n.url.contains(|c: char| c.is_whitespace() || c == '<' || c == '>');
+        if autolink {
+          out.push('<');
+          out.push_str(text.unwrap_or_default());
+          out.push('>');
+        } else {
+          out.push('[');
+          self.children(&n.children, out);
+          out.push_str("](");
+          out.push_str(&destination(&n.url));
+          out.push_str(&title(n.title.as_deref()));
+          out.push(')');
+        }
+      }
+      Node::Image(n) => {
+        out.push_str("![");
+        self.text(&n.alt, out);
+        out.push_str("](");
+        out.push_str(&destination(&n.url));
+        out.push_str(&title(n.title.as_deref()));
+        out.push(')');
+      }
+      Node::LinkReference(n) => {
+        escape_bang(out);
+        out.push('[');
+        self.children(&n.children, out);
+        out.push(']');
+        out.push_str(&reference(n.reference_kind, n.label.as_deref().unwrap_or(&n.identifier)));
+      }
+      Node::ImageReference(n) => {
+        out.push_str("![");
+        self.text(&n.alt, out);
+        out.push(']');
+        out.push_str(&reference(n.reference_kind, n.label.as_deref().unwrap_or(&n.identifier)));
+      }
+      Node::FootnoteReference(n) => {
+        out.push_str("[^");
+        out.push_str(n.label.as_deref().unwrap_or(&n.identifier));
+        out.push(']');
+      }
+      Node::MdxTextExpression(n) => {
+        out.push('{');
+        out.push_str(&n.value);
+        out.push('}');
+      }
+      Node::MdxJsxTextElement(n) => {
+        let open = jsx_open(n.name.as_deref(), &n.attributes);
+        let close = jsx_close(n.name.as_deref());
+        if n.children.is_empty() {
+          out.push_str(&self_closing(&open, n.name.is_some(), &close));
+        } else {
+          out.push_str(&open);
+          self.children(&n.children, out);
+          out.push_str(&close);
+        }
+      }
+      // flow content in phrasing, e.g. from a transform
+      other => out.push_str(&self.block(other, false)),
+    }
+  }
+
+  fn children(&self, nodes: &[Node], out: &mut String) {
+    for node in nodes {
+      self.inline(node, out);
+    }
+  }
+
+  /// Append `value`, escaping what would be taken for markup.
+  fn text(&self, value: &str, out: &mut String) {
+    let chars = value.chars().collect::<Vec<char>>();
+    let mut index = 0;
+    while index < chars.len() {
+      let c = chars[index];
+      let previous = if index == 0 { out.chars().last() } else { Some(chars[index - 1]) };
+      let next = chars.get(index + 1).copied();
+      let line_start = matches!(previous, None | Some('\n'));
+
+      if self.wrapping.get() && matches!(c, ' ' | '\n') {
+        out.push(BREAK);
+        index += 1;
+        continue;
+      }
+
+      if line_start && c.is_ascii_digit() {
+        // `1.` and `1)` start ordered lists
+        let digits = chars[index..].iter().take_while(|c| c.is_ascii_digit()).count();
+        if digits <= 9 && matches!(chars.get(index + digits), Some('.' | ')')) {
+          out.extend(&chars[index..index + digits]);
+          out.push('\\');
+          out.push(chars[index + digits]);
+          index += digits + 1;
+          continue;
+        }
+      }
+
+      let escape = match c {
+        '\\' | '`' | '*' | '[' | ']' => true,
+        '_' => !(previous.is_some_and(char::is_alphanumeric) && next.is_some_and(char::is_alphanumeric)),
+        '<' => next.is_some_and(|n| n.is_alphabetic() || matches!(n, '/' | '!' | '?' | '>')),
+        '&' => entity_like(&chars[index + 1..]),
+        '{' | '}' => self.constructs.mdx_expression_flow || self.constructs.mdx_expression_text,
+        '|' => self.constructs.gfm_table,
+        '~' => line_start || self.constructs.gfm_strikethrough,
+        '$' => self.constructs.math_text || self.constructs.math_flow,
+        '#' | '>' | '-' | '+' | '=' => line_start,
+        // break up what GFM would turn into links: `http://`, `www.`, and
+        // emails
+        ':' if self.constructs.gfm_autolink_literal => {
+          let before = chars[..index].iter().collect::<String>().to_ascii_lowercase();
+          (before.ends_with("http") || before.ends_with("https")) && chars[index + 1..].starts_with(&['/', '/'])
+        }
+        '.' if self.constructs.gfm_autolink_literal => {
+          let before = chars[..index].iter().rev().take(4).collect::<String>().to_ascii_lowercase();
+          before.starts_with("www") && !before[3..].starts_with(char::is_alphanumeric)
+        }
+        '@' => {
+          self.constructs.gfm_autolink_literal
+            && previous.is_some_and(char::is_alphanumeric)
+            && next.is_some_and(char::is_alphanumeric)
+        }
+        _ => false,
+      };
+      if escape {
+        out.push('\\');
+      }
+      out.push(c);
+      index += 1;
+    }
+  }
+}
+
+/// Paragraph text with its `BREAK`s turned into spaces, or, for
+/// `ProseWrap::Always`, into line endings where lines would get longer than
+/// `width`.
+fn fill(text: &str, wrap: ProseWrap, width: usize) -> String {
+  text
+    .split('\n')
+    .map(|line| {
+      let mut lines = vec![];
+      let mut current = String::new();
+      for (index, word) in line.split(BREAK).enumerate() {
+        let fits = current.chars().count() + 1 + word.chars().count() <= width;
+        if index == 0 {
+          current.push_str(word);
+        } else if wrap == ProseWrap::Always && !fits && breakable(word) && !current.trim().is_empty() {
+          lines.push(current.trim_end().to_owned());
+          current = word.to_owned();
+        } else {
+          current.push(' ');
+          current.push_str(word);
+        }
+      }
+      lines.push(current);
+      lines.join("\n")
+    })
+    .collect::<Vec<String>>()
+    .join("\n")
+}
+
+/// Whether a line can start with `word`: not with what could start a list
+/// item, heading, block quote or HTML, so only with letters and a few
+/// marks.
+fn breakable(word: &str) -> bool {
+  word.starts_with(|c: char| c.is_alphabetic() || matches!(c, '(' | '"' | '\'' | '[' | '*' | '_' | '!'))
+}
+
+/// `content` with `first` before its first line and `rest` before the
+/// others; blank lines get only the non-whitespace part of the prefix.
+fn indent(content: &str, first: &str, rest: &str) -> String {
+  content
+    .split('\n')
+    .enumerate()
+    .map(|(index, line)| {
+      let prefix = if index == 0 { first } else { rest };
+      if line.is_empty() {
+        prefix.trim_end().to_owned()
+      } else {
+        format!("{}{}", prefix, line)
+      }
+    })
+    .collect::<Vec<String>>()
+    .join("\n")
+}
+
+/// A fenced block of `value`, with a fence longer than any run of `marker`
+/// in it.
+fn fenced(marker: char, min: usize, info: &str, value: &str) -> String {
+  let size = longest_run(value, marker).max(min - 1) + 1;
+  let fence = marker.to_string().repeat(size);
+  if value.is_empty() {
+    format!("{}{}\n{}", fence, info, fence)
+  } else {
+    format!("{}{}\n{}\n{}", fence, info, value, fence)
+  }
+}
+
+fn longest_run(value: &str, marker: char) -> usize {
+  let mut longest = 0;
+  let mut run = 0;
+  for c in value.chars() {
+    run = if c == marker { run + 1 } else { 0 };
+    longest = longest.max(run);
+  }
+  longest
+}
+
+/// Inline code or math: `value` between runs of `marker` that do not occur
+/// in it.
+fn code_span(marker: char, value: &str) -> String {
+  let mut runs = vec![];
+  let mut run = 0;
+  for c in value.chars().chain(['\0']) {
+    if c == marker {
+      run += 1;
+    } else if run > 0 {
+      runs.push(run);
+      run = 0;
+    }
+  }
+  let mut size = 1;
+  while runs.contains(&size) {
+    size += 1;
+  }
+  let fence = marker.to_string().repeat(size);
+  // one space on both sides is stripped when parsing
+  let pad = value.starts_with(marker)
+    || value.ends_with(marker)
+    || (value.starts_with(' ') && value.ends_with(' ') && !value.trim().is_empty());
+  let space = if pad { " " } else { "" };
+  format!("{}{}{}{}{}", fence, space, value, space, fence)
+}
+
+fn destination(url: &str) -> String {
+  let url = escape_entities(&url.replace('\\', "\\\\"));
+  if url.is_empty() || url.contains(|c: char| c.is_whitespace() || c.is_control() || matches!(c, '(' | ')' | '<' | '>')) {
+    format!("<{}>", url.replace('<', "\\<").replace('>', "\\>"))
+  } else {
+    url
+  }
+}
+
+fn title(title: Option<&str>) -> String {
+  match title {
+    Some(title) => format!(" \"{}\"", escape_entities(&title.replace('\\', "\\\\").replace('"', "\\\""))),
+    None => String::new(),
+  }
+}
+
+fn reference(kind: ReferenceKind, label: &str) -> String {
+  match kind {
+    ReferenceKind::Full => format!("[{}]", label),
+    ReferenceKind::Collapsed => "[]".to_owned(),
+    ReferenceKind::Shortcut => String::new(),
+  }
+}
+
+/// `value` with a `\` before every `&` that starts a character reference.
+fn escape_entities(value: &str) -> String {
+  let chars = value.chars().collect::<Vec<char>>();
+  let mut out = String::with_capacity(value.len());
+  for (index, c) in chars.iter().enumerate() {
+    if *c == '&' && entity_like(&chars[index + 1..]) {
+      out.push('\\');
+    }
+    out.push(*c);
+  }
+  out
+}
+
+/// Whether `rest`, after a `&`, makes a character reference such as `amp;`.
+fn entity_like(rest: &[char]) -> bool {
+  let name = rest.iter().take_while(|c| c.is_ascii_alphanumeric() || **c == '#').count();
+  name > 0 && rest.get(name) == Some(&';')
+}
+
+/// A `!` right before a link would make it an image.
+fn escape_bang(out: &mut String) {
+  if out.ends_with('!') && !out.ends_with("\\!") {
+    out.insert(out.len() - 1, '\\');
+  }
+}
+
+fn jsx_open(name: Option<&str>, attributes: &[AttributeContent]) -> String {
+  let mut open = format!("<{}", name.unwrap_or_default());
+  for attribute in attributes {
+    open.push(' ');
+    match attribute {
+      AttributeContent::Expression(value, _) => {
+        open.push('{');
+        open.push_str(value);
+        open.push('}');
+      }
+      AttributeContent::Property(p) => {
+        open.push_str(&p.name);
+        match &p.value {
+          Some(AttributeValue::Literal(value)) if value.contains('"') && !value.contains('\'') => {
+            open.push_str(&format!("='{}'", value));
+          }
+          Some(AttributeValue::Literal(value)) => {
+            open.push_str(&format!("=\"{}\"", value.replace('"', "&quot;")));
+          }
+          Some(AttributeValue::Expression(value, _)) => open.push_str(&format!("={{{}}}", value)),
+          None => {}
+        }
+      }
+    }
+  }
+  open.push('>');
+  open
+}
+
+fn jsx_close(name: Option<&str>) -> String {
+  format!("</{}>", name.unwrap_or_default())
+}
+
+/// An element without children: `<a />`, but `<></>` for fragments.
+fn self_closing(open: &str, named: bool, close: &str) -> String {
+  if named {
+    format!("{} />", &open[..open.len() - 1])
+  } else {
+    format!("{}{}", open, close)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::Syntax;
+  use crate::visit::{walk_mut, Control};
+  use markdown::unist::Position;
+
+  /// `tree` without positions, which differ between the original and the
+  /// output.
+  fn without_positions(mut tree: Node) -> Node {
+    let blank = |stops: &mut Vec<markdown::mdast::Stop>| stops.clear();
+    walk_mut(&mut tree, |node, _| {
+      if let Some(position) = node.position_mut() {
+        *position = Position::new(1, 1, 0, 1, 1, 0);
+      }
+      match node {
+        Node::MdxjsEsm(n) => blank(&mut n.stops),
+        Node::MdxFlowExpression(n) => blank(&mut n.stops),
+        Node::MdxTextExpression(n) => blank(&mut n.stops),
+        Node::MdxJsxFlowElement(n) => n.attributes.iter_mut().for_each(blank_attribute),
+        Node::MdxJsxTextElement(n) => n.attributes.iter_mut().for_each(blank_attribute),
+        _ => {}
+      }
+      Control::Continue
+    });
+    tree
+  }
+
+  fn blank_attribute(attribute: &mut AttributeContent) {
+    match attribute {
+      AttributeContent::Expression(_, stops) => stops.clear(),
+      AttributeContent::Property(p) => {
+        if let Some(AttributeValue::Expression(_, stops)) = &mut p.value {
+          stops.clear();
+        }
+      }
+    }
+  }
+
+  fn round_trip(source: &str, syntax: Syntax, options: &StringifyOptions) -> String {
+    let parse = syntax.parse_options();
+    let tree = markdown::to_mdast(source, &parse).unwrap();
+    let output = to_markdown(&tree, &parse.constructs, options);
+    let again = markdown::to_mdast(&output, &parse).unwrap();
+    assert_eq!(
+      without_positions(again),
+      without_positions(tree),
+      "source:\n{}\noutput:\n{}",
+      source,
+      output
+    );
+    output
+  }
+
+  const MARKDOWN: &str = r#"Setext
+======
+
+## ATX *emphasis* and __strong__ #
+
+Text with \*stars\*, [brackets], <not html, a_b_c, _under_, 1\. and &amp; a \\ backslash.
+A second line with a hard break\
+and `code`, `` a ` tick ``, and ` `` `.
+
++ plus
++ items
+
+* another list
+
+1) one
+2) two
+
+10. ten
+
+- [link](https://example.com "title") and ![image](a.png 'alt title')
+- <https://example.com> and <a@b.c>
+- [full][Def], [collapsed][], [shortcut] and ![img ref][def]
+
+  - nested *and*
+    loose
+
+  - item
+
+> quote
+> > nested
+>
+> ```rust title="a"
+> fn main() {}
+> ```
+
+~~~
+```
+~~~
+
+    indented
+
+***
+
+<div>
+html
+</div>
+
+[def]: <a b> "t \"q\""
+[shortcut]: /s
+[collapsed]: /c
+"#;
+
+  const GFM: &str = r#"# Tables
+
+| a | b | c | d |
+| :- | :-: | -: | - |
+| 1 | **2** | `3` | 4 \| 5 |
+
+~~strike~~ and https://example.com and www.example.com.
+
+- [ ] todo
+- [x] done
+
+Footnote[^1] and [^note].
+
+[^1]: One.
+
+[^note]: Two paragraphs.
+
+    Second.
+"#;
+
+  const MDX: &str = r#"import {Chart} from './chart.js'
+export const meta = {a: 1}
+
+# Hi {props.name}
+
+<Note type="warning" open data={{a: 1}} {...rest}>
+  Some *text* with <Badge color='red'>new</Badge> and {1 + 1}.
+
+  <Nested />
+</Note>
+
+<>
+  fragment
+</>
+
+Escaped \{braces\} and \< less.
+
+{/* comment */}
+"#;
+
+  const MATH: &str = "Inline $a^2$ and $$b$$.\n\n$$\nc = 1\n$$\n\nDollar \\$5.\n";
+
+  #[test]
+  fn round_trips_fixtures() {
+    let gfm = Syntax {
+      gfm: true,
+      ..Syntax::default()
+    };
+    let mdx = Syntax {
+      mdx: true,
+      ..Syntax::default()
+    };
+    let math = Syntax {
+      math: true,
+      ..Syntax::default()
+    };
+    let underscores = StringifyOptions {
+      bullet: '*',
+      emphasis: '_',
+      strong: '_',
+      fence: '~',
+      rule: '*',
+      heading: HeadingStyle::Setext,
+      ..StringifyOptions::default()
+    };
+    for options in [StringifyOptions::default(), underscores] {
+      round_trip(MARKDOWN, Syntax::default(), &options);
+      round_trip(GFM, gfm, &options);
+      round_trip(MDX, mdx, &options);
+      round_trip(MATH, math, &options);
+    }
+  }
+
+  #[test]
+  fn writes_style() {
+    let output = round_trip(
+      "* a *b* __c__\n\n---\n\n~~~js\nx\n~~~\n",
+      Syntax::default(),
+      &StringifyOptions::default(),
+    );
+    assert_eq!(output, "- a *b* **c**\n\n---\n\n```js\nx\n```\n");
+
+    let gfm = Syntax {
+      gfm: true,
+      ..Syntax::default()
+    };
+    let output = round_trip("|a|b|\n|:-:|--:|\n|long cell|x|\n", gfm, &StringifyOptions::default());
+    assert_eq!(
+      output,
+      "|     a     |   b |\n| :-------: | --: |\n| long cell |   x |\n"
+    );
+
+    let invalid = StringifyOptions {
+      bullet: '#',
+      ..StringifyOptions::default()
+    };
+    assert!(invalid.validate().is_err());
+  }
+}
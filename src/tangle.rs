@@ -0,0 +1,287 @@
+//! Write code blocks out to files ("tangle" them).
+//!
+//! A block is written when its fence meta names a file:
+//!
+//! ````md
+//! ```hcl filename="main.tf"
+//! ```
+//! ````
+//!
+//! Blocks naming the same file are appended to it in document order, across
+//! all documents processed.
+
+use crate::visit::{walk, Control};
+use crate::wrappers::MyPosition;
+use crate::Document;
+use markdown::mdast::Node;
+use markdown::unist::Position;
+
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// The `key=value` pairs of a fence meta string. Values may be quoted with
+/// `"` or `'`; a key without a value gets an empty one.
+pub fn parse_meta(meta: &str) -> Vec<(String, String)> {
+  let mut pairs = vec![];
+  let mut rest = meta.trim_start();
+  while !rest.is_empty() {
+    let key_end = rest.find(|c: char| c == '=' || c.is_whitespace()).unwrap_or(rest.len());
+    let key = &rest[..key_end];
+    rest = &rest[key_end..];
+
+    let value = match rest.strip_prefix('=') {
+      Some(after) => {
+        let (value, after) = match after.chars().next() {
+          Some(quote @ ('"' | '\'')) => match after[1..].find(quote) {
+            Some(end) => (&after[1..end + 1], &after[end + 2..]),
+            None => (&after[1..], ""),
+          },
+          _ => {
+            let end = after.find(char::is_whitespace).unwrap_or(after.len());
+            (&after[..end], &after[end..])
+          }
+        };
+        rest = after;
+        value
+      }
+      None => "",
+    };
+    if !key.is_empty() {
+      pairs.push((key.to_owned(), value.to_owned()));
+    }
+    rest = rest.trim_start();
+  }
+  pairs
+}
+
+/// Which code blocks are written.
+#[derive(Debug, Clone, Default)]
+pub struct TangleOptions {
+  /// Languages to write; all when empty.
+  pub langs: Vec<String>,
+  /// Patterns of file names to write, with `*` and `?` wildcards; all when
+  /// empty.
+  pub paths: Vec<String>,
+}
+
+/// A code block, and where it comes from.
+#[derive(Debug, Clone)]
+pub struct Block {
+  /// The document the block is in.
+  pub file: Option<PathBuf>,
+  pub lang: Option<String>,
+  pub value: String,
+  pub position: Option<Position>,
+}
+
+/// A file to write, and the blocks that make it up.
+#[derive(Debug, Clone)]
+pub struct Output {
+  /// Relative to the output directory.
+  pub path: PathBuf,
+  pub blocks: Vec<Block>,
+}
+
+impl Output {
+  pub fn contents(&self) -> String {
+    let mut contents = String::new();
+    for block in &self.blocks {
+      contents.push_str(&block.value);
+      contents.push('\n');
+    }
+    contents
+  }
+}
+
+impl Serialize for Block {
+  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    let mut state = serializer.serialize_struct("Block", 3)?;
+    state.serialize_field("file", &self.file)?;
+    state.serialize_field("lang", &self.lang)?;
+    if let Some(p) = self.position.clone() {
+      let pos: MyPosition = MyPosition::from(p);
+      state.serialize_field("position", &pos)?;
+    }
+    state.end()
+  }
+}
+
+/// Serialized as an entry of the manifest: the blocks, without their code.
+impl Serialize for Output {
+  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    let mut state = serializer.serialize_struct("Output", 2)?;
+    state.serialize_field("path", &self.path)?;
+    state.serialize_field("blocks", &self.blocks)?;
+    state.end()
+  }
+}
+
+/// Group the code blocks of `documents` by the file they name.
+pub fn tangle(documents: &[Document], options: &TangleOptions) -> Result<Vec<Output>, String> {
+  let mut outputs: Vec<Output> = vec![];
+  for document in documents {
+    let mut error = None;
+    walk(&document.tree, |node, _| {
+      let code = match node {
+        Node::Code(code) => code,
+        _ => return Control::Continue,
+      };
+      let filename = match code.meta.as_deref().map(parse_meta).and_then(|meta| {
+        meta.into_iter().find(|(key, _)| key == "filename").map(|(_, value)| value)
+      }) {
+        Some(filename) => filename,
+        None => return Control::Continue,
+      };
+
+      let lang_matches = options.langs.is_empty() || options.langs.iter().any(|l| Some(l) == code.lang.as_ref());
+      let path_matches = options.paths.is_empty() || options.paths.iter().any(|p| glob(p, &filename));
+      if !lang_matches || !path_matches {
+        return Control::Continue;
+      }
+
+      let path = match safe_path(&filename) {
+        Some(path) => path,
+        None => {
+          let at = code.position.as_ref().map(|p| p.start.line).unwrap_or_default();
+          error = Some(format!(
+            "{}:{}: `{}` must be a relative path inside the output directory",
+            document.path.as_deref().unwrap_or_else(|| Path::new("")).display(),
+            at,
+            filename
+          ));
+          return Control::Stop;
+        }
+      };
+      let block = Block {
+        file: document.path.clone(),
+        lang: code.lang.clone(),
+        value: code.value.clone(),
+        position: code.position.clone(),
+      };
+      match outputs.iter_mut().find(|o| o.path == path) {
+        Some(output) => output.blocks.push(block),
+        None => outputs.push(Output {
+          path,
+          blocks: vec![block],
+        }),
+      }
+      Control::Continue
+    });
+    if let Some(error) = error {
+      return Err(error);
+    }
+  }
+  Ok(outputs)
+}
+
+/// Write `outputs` in `dir`, creating directories as needed.
+pub fn write(outputs: &[Output], dir: &Path) -> Result<(), String> {
+  for output in outputs {
+    let path = dir.join(&output.path);
+    if let Some(parent) = path.parent() {
+      fs::create_dir_all(parent).map_err(|e| format!("{}: {}", parent.display(), e))?;
+    }
+    fs::write(&path, output.contents()).map_err(|e| format!("{}: {}", path.display(), e))?;
+  }
+  Ok(())
+}
+
+/// `filename` as a path, if it stays inside the directory it is joined to.
+fn safe_path(filename: &str) -> Option<PathBuf> {
+  let path = PathBuf::from(filename);
+  let normal = path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+  if normal && path.components().any(|c| matches!(c, Component::Normal(_))) {
+    Some(path.components().collect())
+  } else {
+    None
+  }
+}
+
+/// Whether `value` matches `pattern`, where `*` matches any run of
+/// characters and `?` any one character.
+pub(crate) fn glob(pattern: &str, value: &str) -> bool {
+  let pattern = pattern.chars().collect::<Vec<char>>();
+  let value = value.chars().collect::<Vec<char>>();
+  let (mut p, mut v) = (0, 0);
+  // where the last `*` was, and how much of `value` it has taken
+  let mut star: Option<(usize, usize)> = None;
+  while v < value.len() {
+    match pattern.get(p) {
+      Some('*') => {
+        star = Some((p, v));
+        p += 1;
+      }
+      Some(&c) if c == '?' || c == value[v] => {
+        p += 1;
+        v += 1;
+      }
+      _ => match star {
+        Some((star_p, star_v)) => {
+          p = star_p + 1;
+          v = star_v + 1;
+          star = Some((star_p, star_v + 1));
+        }
+        None => return false,
+      },
+    }
+  }
+  pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{parse_str, Options};
+
+  #[test]
+  fn parses_meta() {
+    assert_eq!(
+      parse_meta(r#"filename="main.tf" title='A b' highlight=1-3 nocheck"#),
+      vec![
+        ("filename".to_owned(), "main.tf".to_owned()),
+        ("title".to_owned(), "A b".to_owned()),
+        ("highlight".to_owned(), "1-3".to_owned()),
+        ("nocheck".to_owned(), String::new()),
+      ]
+    );
+  }
+
+  #[test]
+  fn groups_blocks_by_file() {
+    let source = "```hcl filename=\"main.tf\"\na\n```\n\n```sh filename=run.sh\nb\n```\n\n```hcl filename=\"main.tf\"\nc\n```\n\n```hcl\nd\n```\n";
+    let documents = vec![parse_str(source, &Options::default()).unwrap()];
+
+    let outputs = tangle(&documents, &TangleOptions::default()).unwrap();
+    let files = outputs
+      .iter()
+      .map(|o| (o.path.to_str().unwrap(), o.contents()))
+      .collect::<Vec<_>>();
+    assert_eq!(files, vec![("main.tf", "a\nc\n".to_owned()), ("run.sh", "b\n".to_owned())]);
+    assert_eq!(outputs[0].blocks[1].position.as_ref().unwrap().start.line, 9);
+
+    let options = TangleOptions {
+      langs: vec!["hcl".to_owned(), "sh".to_owned()],
+      paths: vec!["*.sh".to_owned()],
+    };
+    let outputs = tangle(&documents, &options).unwrap();
+    assert_eq!(outputs.len(), 1);
+    assert_eq!(outputs[0].path, PathBuf::from("run.sh"));
+
+    let escaping = parse_str("```sh filename=../x.sh\n```\n", &Options::default()).unwrap();
+    assert!(tangle(&[escaping], &TangleOptions::default()).is_err());
+  }
+
+  #[test]
+  fn globs() {
+    assert!(glob("*.tf", "main.tf"));
+    assert!(glob("modules/*/main.?f", "modules/a/main.tf"));
+    assert!(!glob("*.tf", "main.tfvars"));
+  }
+}
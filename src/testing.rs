@@ -0,0 +1,37 @@
+//! Helpers shared by the tests of several modules.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A directory of files for a test. It is removed when dropped, so also
+/// when an assertion fails.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+  /// An empty directory, named after `name` and the running process.
+  pub fn new(name: &str) -> TempDir {
+    let path = std::env::temp_dir().join(format!("markdown-rs-test-{}-{}", name, std::process::id()));
+    // left over from a run that was killed
+    let _ = fs::remove_dir_all(&path);
+    fs::create_dir_all(&path).unwrap();
+    TempDir(path)
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.0
+  }
+
+  /// Write `contents` to `name`, creating the directories in between.
+  pub fn write(&self, name: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+    let path = self.0.join(name);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, contents).unwrap();
+    path
+  }
+}
+
+impl Drop for TempDir {
+  fn drop(&mut self) {
+    let _ = fs::remove_dir_all(&self.0);
+  }
+}
@@ -0,0 +1,170 @@
+//! Plain text of mdast nodes, for search snippets, labels and descriptions.
+//!
+//! ```
+//! use markdown_rs_test::text::{to_text, TextOptions};
+//!
+//! let tree = markdown::to_mdast("# Hello *world*\n\nUse `cargo`.", &markdown::ParseOptions::default())?;
+//! assert_eq!(to_text(&tree, &TextOptions::default()), "Hello world\n\nUse cargo.");
+//! # Ok::<(), String>(())
+//! ```
+
+use crate::section;
+use crate::visit::{type_name, visit, Context, Control, Visitor};
+use markdown::mdast::Node;
+
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct TextOptions {
+  /// Put between blocks: paragraphs, headings, code, list items, table rows.
+  pub block_separator: String,
+  /// Turn every run of whitespace into a single space, and trim blocks.
+  pub collapse_whitespace: bool,
+  /// Include the source of `Html` nodes.
+  pub include_html: bool,
+  /// Include the text inside JSX elements.
+  pub include_jsx: bool,
+  /// Types of nodes to leave out, with everything in them, e.g. `Code`.
+  pub skip: Vec<String>,
+}
+
+impl Default for TextOptions {
+  fn default() -> TextOptions {
+    TextOptions {
+      block_separator: "\n\n".to_owned(),
+      collapse_whitespace: true,
+      include_html: false,
+      include_jsx: false,
+      skip: vec![],
+    }
+  }
+}
+
+/// The text of `node`: the values of `Text`, `InlineCode` and `Code` nodes,
+/// and of `Html` and JSX if asked for.
+pub fn to_text(node: &Node, options: &TextOptions) -> String {
+  let mut extractor = Extractor {
+    options,
+    blocks: vec![String::new()],
+  };
+  visit(node, &mut extractor);
+
+  extractor
+    .blocks
+    .into_iter()
+    .map(|block| {
+      if options.collapse_whitespace {
+        block.split_whitespace().collect::<Vec<&str>>().join(" ")
+      } else {
+        block
+      }
+    })
+    .filter(|block| !block.is_empty())
+    .collect::<Vec<String>>()
+    .join(&options.block_separator)
+}
+
+struct Extractor<'o> {
+  options: &'o TextOptions,
+  blocks: Vec<String>,
+}
+
+impl Extractor<'_> {
+  fn push(&mut self, value: &str) {
+    self.blocks.last_mut().unwrap().push_str(value);
+  }
+
+  fn end_block(&mut self) {
+    if !self.blocks.last().unwrap().is_empty() {
+      self.blocks.push(String::new());
+    }
+  }
+}
+
+impl<'n> Visitor<'n> for Extractor<'_> {
+  fn enter(&mut self, node: &'n Node, _context: &Context<'_, 'n>) -> Control {
+    let name = type_name(node);
+    if self.options.skip.iter().any(|s| s.eq_ignore_ascii_case(name)) {
+      return Control::Skip;
+    }
+    if is_block(node) {
+      self.end_block();
+    }
+    match node {
+      Node::Text(n) => self.push(&n.value),
+      Node::InlineCode(n) => self.push(&n.value),
+      Node::Code(n) => self.push(&n.value),
+      Node::Break(_) => self.push("\n"),
+      Node::Html(n) if self.options.include_html => self.push(&n.value),
+      Node::MdxJsxFlowElement(_) | Node::MdxJsxTextElement(_) if !self.options.include_jsx && !section::is_section(node) => {
+        return Control::Skip
+      }
+      Node::TableCell(_) if !self.blocks.last().unwrap().is_empty() => self.push(" "),
+      _ => {}
+    }
+    Control::Continue
+  }
+
+  fn exit(&mut self, node: &'n Node, _context: &Context<'_, 'n>) -> Control {
+    if is_block(node) {
+      self.end_block();
+    }
+    Control::Continue
+  }
+}
+
+fn is_block(node: &Node) -> bool {
+  matches!(
+    node,
+    Node::Root(_)
+      | Node::BlockQuote(_)
+      | Node::FootnoteDefinition(_)
+      | Node::MdxJsxFlowElement(_)
+      | Node::List(_)
+      | Node::ListItem(_)
+      | Node::Code(_)
+      | Node::Math(_)
+      | Node::Heading(_)
+      | Node::Table(_)
+      | Node::TableRow(_)
+      | Node::Paragraph(_)
+      | Node::ThematicBreak(_)
+      | Node::Definition(_)
+      | Node::MdxFlowExpression(_)
+      | Node::MdxjsEsm(_)
+      | Node::Toml(_)
+      | Node::Yaml(_)
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn tree(source: &str) -> Node {
+    let options = markdown::ParseOptions {
+      constructs: markdown::Constructs {
+        gfm_table: true,
+        ..markdown::Constructs::mdx()
+      },
+      ..markdown::ParseOptions::default()
+    };
+    markdown::to_mdast(source, &options).unwrap()
+  }
+
+  #[test]
+  fn separates_blocks() {
+    let tree = tree("# A\n\n- b\n- c  d\n\n| e | f |\n| - | - |\n| g | h |\n\n<Note>\n  i\n</Note>\n\n```js\nj\n```\n");
+    assert_eq!(to_text(&tree, &TextOptions::default()), "A\n\nb\n\nc d\n\ne f\n\ng h\n\nj");
+
+    let options = TextOptions {
+      block_separator: " | ".to_owned(),
+      collapse_whitespace: false,
+      include_jsx: true,
+      skip: vec!["code".to_owned(), "Table".to_owned()],
+      ..TextOptions::default()
+    };
+    assert_eq!(to_text(&tree, &options), "A | b | c  d | i");
+  }
+}
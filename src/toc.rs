@@ -0,0 +1,158 @@
+//! Table of contents built from the headings of a document.
+
+use crate::section;
+use crate::slug;
+use crate::visit::{walk, Control};
+use crate::wrappers::MyPosition;
+use markdown::mdast::Node;
+use markdown::unist::Position;
+
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+
+/// Which headings go in the table of contents.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct TocOptions {
+  /// Shallowest heading depth to include.
+  pub min_depth: u8,
+  /// Deepest heading depth to include.
+  pub max_depth: u8,
+  /// Leave out headings inside JSX elements, e.g. in `<Tabs>`.
+  pub exclude_jsx: bool,
+}
+
+impl Default for TocOptions {
+  fn default() -> TocOptions {
+    TocOptions {
+      min_depth: 1,
+      max_depth: 6,
+      exclude_jsx: false,
+    }
+  }
+}
+
+/// A heading in the table of contents, with the headings under it.
+#[derive(Debug, Clone)]
+pub struct Entry {
+  pub depth: u8,
+  /// Plain text of the heading.
+  pub text: String,
+  pub slug: String,
+  pub position: Option<Position>,
+  pub children: Vec<Entry>,
+}
+
+impl Serialize for Entry {
+  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    let mut state = serializer.serialize_struct("Entry", 5)?;
+    state.serialize_field("depth", &self.depth)?;
+    state.serialize_field("text", &self.text)?;
+    state.serialize_field("slug", &self.slug)?;
+    if let Some(p) = self.position.clone() {
+      let pos: MyPosition = MyPosition::from(p);
+      state.serialize_field("position", &pos)?;
+    }
+    state.serialize_field("children", &self.children)?;
+    state.end()
+  }
+}
+
+/// The table of contents of `tree`; `ids` are the ids of its headings, in
+/// document order, as made by `slug::heading_ids`.
+///
+/// A heading is nested under the closest heading before it with a smaller
+/// depth, so skipped levels (`#` then `###`) do not need filler entries.
+pub fn toc(tree: &Node, ids: &[String], options: &TocOptions) -> Vec<Entry> {
+  let mut flat = vec![];
+  let mut index = 0;
+  walk(tree, |node, context| {
+    if let Node::Heading(heading) = node {
+      let in_jsx = context
+        .ancestors
+        .iter()
+        .any(|a| matches!(a, Node::MdxJsxFlowElement(_) | Node::MdxJsxTextElement(_)) && !section::is_section(a));
+      let id = ids.get(index).cloned().unwrap_or_default();
+      index += 1;
+
+      if (options.min_depth..=options.max_depth).contains(&heading.depth) && !(options.exclude_jsx && in_jsx) {
+        flat.push(Entry {
+          depth: heading.depth,
+          text: slug::text(node),
+          slug: id,
+          position: heading.position.clone(),
+          children: vec![],
+        });
+      }
+      return Control::Skip;
+    }
+    Control::Continue
+  });
+  nest(flat)
+}
+
+fn nest(flat: Vec<Entry>) -> Vec<Entry> {
+  // entries whose children are still being collected, shallowest first
+  let mut open: Vec<Entry> = vec![];
+  let mut roots = vec![];
+
+  fn close(entry: Entry, open: &mut [Entry], roots: &mut Vec<Entry>) {
+    match open.last_mut() {
+      Some(parent) => parent.children.push(entry),
+      None => roots.push(entry),
+    }
+  }
+
+  for entry in flat {
+    while matches!(open.last(), Some(e) if e.depth >= entry.depth) {
+      let done = open.pop().unwrap();
+      close(done, &mut open, &mut roots);
+    }
+    open.push(entry);
+  }
+  while let Some(done) = open.pop() {
+    close(done, &mut open, &mut roots);
+  }
+  roots
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn outline(entries: &[Entry]) -> Vec<String> {
+    let mut lines = vec![];
+    fn add(entries: &[Entry], level: usize, lines: &mut Vec<String>) {
+      for entry in entries {
+        lines.push(format!("{}{} #{}", "  ".repeat(level), entry.text, entry.slug));
+        add(&entry.children, level + 1, lines);
+      }
+    }
+    add(entries, 0, &mut lines);
+    lines
+  }
+
+  #[test]
+  fn nests_headings() {
+    let source = "# A\n\n### B\n\n## C\n\n#### D\n\n# E\n\n<Tabs>\n\n## F\n\n</Tabs>\n";
+    let options = markdown::ParseOptions {
+      constructs: markdown::Constructs::mdx(),
+      ..markdown::ParseOptions::default()
+    };
+    let mut tree = markdown::to_mdast(source, &options).unwrap();
+    let ids = slug::heading_ids(&mut tree);
+
+    let all = toc(&tree, &ids, &TocOptions::default());
+    assert_eq!(outline(&all), vec!["A #a", "  B #b", "  C #c", "    D #d", "E #e", "  F #f"]);
+
+    let options = TocOptions {
+      min_depth: 2,
+      max_depth: 3,
+      exclude_jsx: true,
+    };
+    let filtered = toc(&tree, &ids, &options);
+    assert_eq!(outline(&filtered), vec!["B #b", "C #c"]);
+  }
+}
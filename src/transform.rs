@@ -0,0 +1,222 @@
+//! Changes to the tree between parsing and serialization.
+//!
+//! A `Pipeline` runs `Transform`s in order over the tree of every document.
+//! The built-in transforms are enabled by name in the config file:
+//!
+//! ```json
+//! { "transforms": ["remove-comments", "drop-empty-paragraphs", "merge-text"] }
+//! ```
+
+use crate::diagnostic::Diagnostic;
+use crate::section::Sections;
+use crate::visit::{walk_mut, Control};
+use markdown::mdast::{Node, Text};
+use markdown::unist::Position;
+
+pub trait Transform {
+  /// The name of the transform in the config file.
+  fn name(&self) -> &str;
+
+  /// Change `tree`; problems found along the way go in `diagnostics`.
+  fn transform(&self, tree: &mut Node, diagnostics: &mut Vec<Diagnostic>);
+}
+
+/// Transforms to run, in order.
+#[derive(Default)]
+pub struct Pipeline {
+  transforms: Vec<Box<dyn Transform>>,
+}
+
+impl Pipeline {
+  pub fn new() -> Pipeline {
+    Pipeline::default()
+  }
+
+  pub fn push(&mut self, transform: Box<dyn Transform>) {
+    self.transforms.push(transform);
+  }
+
+  /// A pipeline of the built-in transforms called `names`, in that order.
+  pub fn from_names(names: &[String]) -> Result<Pipeline, String> {
+    let mut pipeline = Pipeline::new();
+    for name in names {
+      pipeline.push(builtin(name).ok_or_else(|| {
+        format!(
+          "unknown transform `{}`, expected one of: {}",
+          name,
+          BUILTINS.join(", ")
+        )
+      })?);
+    }
+    Ok(pipeline)
+  }
+
+  pub fn names(&self) -> Vec<&str> {
+    self.transforms.iter().map(|t| t.name()).collect()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.transforms.is_empty()
+  }
+
+  pub fn run(&self, tree: &mut Node, diagnostics: &mut Vec<Diagnostic>) {
+    for transform in &self.transforms {
+      transform.transform(tree, diagnostics);
+    }
+  }
+}
+
+/// Names of the built-in transforms.
+pub const BUILTINS: &[&str] = &["merge-text", "remove-comments", "drop-empty-paragraphs", "sections"];
+
+pub fn builtin(name: &str) -> Option<Box<dyn Transform>> {
+  match name {
+    "merge-text" => Some(Box::new(MergeText)),
+    "remove-comments" => Some(Box::new(RemoveComments)),
+    "drop-empty-paragraphs" => Some(Box::new(DropEmptyParagraphs)),
+    "sections" => Some(Box::new(Sections)),
+    _ => None,
+  }
+}
+
+/// Join adjacent `Text` nodes into one.
+pub struct MergeText;
+
+impl Transform for MergeText {
+  fn name(&self) -> &str {
+    "merge-text"
+  }
+
+  fn transform(&self, tree: &mut Node, _diagnostics: &mut Vec<Diagnostic>) {
+    walk_mut(tree, |node, _| {
+      if let Some(children) = node.children_mut() {
+        let mut merged: Vec<Node> = Vec::with_capacity(children.len());
+        for child in children.drain(..) {
+          match (merged.last_mut(), child) {
+            (Some(Node::Text(previous)), Node::Text(text)) => {
+              previous.value.push_str(&text.value);
+              previous.position = match (previous.position.take(), text.position) {
+                (Some(a), Some(b)) => Some(Position { start: a.start, end: b.end }),
+                _ => None,
+              };
+            }
+            (_, child) => merged.push(child),
+          }
+        }
+        *children = merged;
+      }
+      Control::Continue
+    });
+  }
+}
+
+/// Remove `Html` nodes that are nothing but an HTML comment.
+pub struct RemoveComments;
+
+fn is_comment(value: &str) -> bool {
+  let value = value.trim();
+  value.starts_with("<!--") && value.ends_with("-->") && !value[4..value.len() - 3].contains("-->")
+}
+
+impl Transform for RemoveComments {
+  fn name(&self) -> &str {
+    "remove-comments"
+  }
+
+  fn transform(&self, tree: &mut Node, _diagnostics: &mut Vec<Diagnostic>) {
+    walk_mut(tree, |node, _| {
+      if let Some(children) = node.children_mut() {
+        children.retain(|child| !matches!(child, Node::Html(html) if is_comment(&html.value)));
+      }
+      Control::Continue
+    });
+  }
+}
+
+/// Remove paragraphs without content, or with only whitespace.
+pub struct DropEmptyParagraphs;
+
+impl Transform for DropEmptyParagraphs {
+  fn name(&self) -> &str {
+    "drop-empty-paragraphs"
+  }
+
+  fn transform(&self, tree: &mut Node, _diagnostics: &mut Vec<Diagnostic>) {
+    walk_mut(tree, |node, _| {
+      if let Some(children) = node.children_mut() {
+        children.retain(|child| match child {
+          Node::Paragraph(p) => !p
+            .children
+            .iter()
+            .all(|c| matches!(c, Node::Text(Text { value, .. }) if value.trim().is_empty())),
+          _ => true,
+        });
+      }
+      Control::Continue
+    });
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn run(names: &[&str], source: &str) -> (Node, Vec<Diagnostic>) {
+    let names = names.iter().map(|n| n.to_string()).collect::<Vec<String>>();
+    let pipeline = Pipeline::from_names(&names).unwrap();
+    let mut tree = markdown::to_mdast(source, &markdown::ParseOptions::default()).unwrap();
+    let mut diagnostics = vec![];
+    pipeline.run(&mut tree, &mut diagnostics);
+    (tree, diagnostics)
+  }
+
+  #[test]
+  fn builtins_run_in_order() {
+    let source = "<!-- generated -->\n\na <!-- x --> b\n\n<!-- only -->\n";
+    let (tree, _) = run(&["remove-comments", "drop-empty-paragraphs", "merge-text"], source);
+    let children = tree.children().unwrap();
+    assert_eq!(children.len(), 1);
+
+    let text = &children[0].children().unwrap()[0];
+    match text {
+      Node::Text(text) => {
+        assert_eq!(text.value, "a  b");
+        let position = text.position.as_ref().unwrap();
+        assert_eq!(&source[position.start.offset..position.end.offset], "a <!-- x --> b");
+      }
+      other => panic!("expected text, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn custom_transforms_report_diagnostics() {
+    struct NoEmphasis;
+    impl Transform for NoEmphasis {
+      fn name(&self) -> &str {
+        "no-emphasis"
+      }
+
+      fn transform(&self, tree: &mut Node, diagnostics: &mut Vec<Diagnostic>) {
+        walk_mut(tree, |node, _| {
+          if let Node::Emphasis(e) = node {
+            diagnostics.push(Diagnostic::error("no-emphasis", "emphasis".into(), e.position.clone()));
+          }
+          Control::Continue
+        });
+      }
+    }
+
+    let mut pipeline = Pipeline::new();
+    pipeline.push(Box::new(NoEmphasis));
+    let mut tree = markdown::to_mdast("*a*", &markdown::ParseOptions::default()).unwrap();
+    let mut diagnostics = vec![];
+    pipeline.run(&mut tree, &mut diagnostics);
+    assert_eq!(pipeline.names(), vec!["no-emphasis"]);
+    assert_eq!(diagnostics.len(), 1);
+  }
+
+  #[test]
+  fn rejects_unknown_names() {
+    assert!(Pipeline::from_names(&["nope".to_owned()]).is_err());
+  }
+}
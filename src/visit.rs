@@ -0,0 +1,325 @@
+//! Walk `markdown::mdast::Node` trees.
+//!
+//! ```
+//! use markdown::mdast::Node;
+//! use markdown_rs_test::visit::{walk, Control};
+//!
+//! let tree = markdown::to_mdast("# a\n\n*b*", &markdown::ParseOptions::default())?;
+//! let mut emphasis_in_heading = false;
+//! walk(&tree, |node, context| {
+//!   if let Node::Emphasis(_) = node {
+//!     emphasis_in_heading |= context.ancestors.iter().any(|a| matches!(a, Node::Heading(_)));
+//!   }
+//!   Control::Continue
+//! });
+//! assert!(!emphasis_in_heading);
+//! # Ok::<(), String>(())
+//! ```
+
+use crate::section;
+use markdown::mdast::Node;
+
+/// What to do after visiting a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+  /// Go on, into the children of the node if entering it.
+  Continue,
+  /// Do not visit the children of the node; only meaningful when entering.
+  /// The node is still exited.
+  Skip,
+  /// Stop the whole traversal; no more nodes are entered or exited.
+  Stop,
+}
+
+/// Where a node is in the tree, during an immutable traversal.
+#[derive(Debug)]
+pub struct Context<'a, 'n> {
+  /// Ancestors of the node, from the root down to its parent.
+  pub ancestors: &'a [&'n Node],
+  /// Index of the node in its parent, of its parent in the grandparent, and
+  /// so on, from the root down.
+  pub path: &'a [usize],
+}
+
+impl<'a, 'n> Context<'a, 'n> {
+  /// Index of the node in its parent; `None` for the root.
+  pub fn index(&self) -> Option<usize> {
+    self.path.last().copied()
+  }
+
+  pub fn parent(&self) -> Option<&'n Node> {
+    self.ancestors.last().copied()
+  }
+}
+
+/// Where a node is in the tree, during a mutable traversal.
+///
+/// The ancestors cannot be borrowed while one of their descendants is being
+/// changed, so only their types are available.
+#[derive(Debug)]
+pub struct ContextMut<'a> {
+  /// Types of the ancestors of the node (see `type_name`), from the root down.
+  pub ancestors: &'a [&'static str],
+  pub path: &'a [usize],
+}
+
+impl<'a> ContextMut<'a> {
+  pub fn index(&self) -> Option<usize> {
+    self.path.last().copied()
+  }
+}
+
+pub trait Visitor<'n> {
+  fn enter(&mut self, _node: &'n Node, _context: &Context<'_, 'n>) -> Control {
+    Control::Continue
+  }
+
+  fn exit(&mut self, _node: &'n Node, _context: &Context<'_, 'n>) -> Control {
+    Control::Continue
+  }
+}
+
+pub trait VisitorMut {
+  /// Called before the children of `node` are visited; changes made to the
+  /// children here are seen by the rest of the traversal.
+  fn enter(&mut self, _node: &mut Node, _context: &ContextMut) -> Control {
+    Control::Continue
+  }
+
+  fn exit(&mut self, _node: &mut Node, _context: &ContextMut) -> Control {
+    Control::Continue
+  }
+}
+
+/// Visit `node` and its descendants, depth-first, in document order.
+pub fn visit<'n, V: Visitor<'n>>(node: &'n Node, visitor: &mut V) -> Control {
+  visit_node(node, &mut vec![], &mut vec![], visitor)
+}
+
+fn visit_node<'n, V: Visitor<'n>>(
+  node: &'n Node,
+  ancestors: &mut Vec<&'n Node>,
+  path: &mut Vec<usize>,
+  visitor: &mut V,
+) -> Control {
+  let context = Context { ancestors, path };
+  match visitor.enter(node, &context) {
+    Control::Stop => return Control::Stop,
+    Control::Skip => {}
+    Control::Continue => {
+      if let Some(children) = node.children() {
+        ancestors.push(node);
+        for (index, child) in children.iter().enumerate() {
+          path.push(index);
+          let control = visit_node(child, ancestors, path, visitor);
+          path.pop();
+          if control == Control::Stop {
+            return Control::Stop;
+          }
+        }
+        ancestors.pop();
+      }
+    }
+  }
+
+  let context = Context { ancestors, path };
+  match visitor.exit(node, &context) {
+    Control::Stop => Control::Stop,
+    _ => Control::Continue,
+  }
+}
+
+/// Visit `node` and its descendants mutably, depth-first, in document order.
+pub fn visit_mut<V: VisitorMut>(node: &mut Node, visitor: &mut V) -> Control {
+  visit_node_mut(node, &mut vec![], &mut vec![], visitor)
+}
+
+fn visit_node_mut<V: VisitorMut>(
+  node: &mut Node,
+  ancestors: &mut Vec<&'static str>,
+  path: &mut Vec<usize>,
+  visitor: &mut V,
+) -> Control {
+  let context = ContextMut { ancestors, path };
+  match visitor.enter(node, &context) {
+    Control::Stop => return Control::Stop,
+    Control::Skip => {}
+    Control::Continue => {
+      let name = type_name(node);
+      if let Some(children) = node.children_mut() {
+        ancestors.push(name);
+        for (index, child) in children.iter_mut().enumerate() {
+          path.push(index);
+          let control = visit_node_mut(child, ancestors, path, visitor);
+          path.pop();
+          if control == Control::Stop {
+            return Control::Stop;
+          }
+        }
+        ancestors.pop();
+      }
+    }
+  }
+
+  let context = ContextMut { ancestors, path };
+  match visitor.exit(node, &context) {
+    Control::Stop => Control::Stop,
+    _ => Control::Continue,
+  }
+}
+
+struct Enter<F>(F);
+
+impl<'n, F: FnMut(&'n Node, &Context<'_, 'n>) -> Control> Visitor<'n> for Enter<F> {
+  fn enter(&mut self, node: &'n Node, context: &Context<'_, 'n>) -> Control {
+    (self.0)(node, context)
+  }
+}
+
+impl<F: FnMut(&mut Node, &ContextMut) -> Control> VisitorMut for Enter<F> {
+  fn enter(&mut self, node: &mut Node, context: &ContextMut) -> Control {
+    (self.0)(node, context)
+  }
+}
+
+/// Call `f` when entering every node.
+pub fn walk<'n, F: FnMut(&'n Node, &Context<'_, 'n>) -> Control>(node: &'n Node, f: F) {
+  visit(node, &mut Enter(f));
+}
+
+/// Call `f` when entering every node, mutably.
+pub fn walk_mut<F: FnMut(&mut Node, &ContextMut) -> Control>(node: &mut Node, f: F) {
+  visit_mut(node, &mut Enter(f));
+}
+
+/// The name of the type of `node`, as used in the `type` field of the JSON
+/// output.
+pub fn type_name(node: &Node) -> &'static str {
+  match node {
+    Node::Root(_) => "Root",
+    Node::BlockQuote(_) => "BlockQuote",
+    Node::FootnoteDefinition(_) => "FootnoteDefinition",
+    Node::MdxJsxFlowElement(_) if section::is_section(node) => "Section",
+    Node::MdxJsxFlowElement(_) => "MdxJsxFlowElement",
+    Node::List(_) => "List",
+    Node::MdxjsEsm(_) => "MdxjsEsm",
+    Node::Toml(_) => "Toml",
+    Node::Yaml(_) => "Yaml",
+    Node::Break(_) => "Break",
+    Node::InlineCode(_) => "InlineCode",
+    Node::InlineMath(_) => "InlineMath",
+    Node::Delete(_) => "Delete",
+    Node::Emphasis(_) => "Emphasis",
+    Node::MdxTextExpression(_) => "MdxTextExpression",
+    Node::FootnoteReference(_) => "FootnoteReference",
+    Node::Html(_) => "Html",
+    Node::Image(_) => "Image",
+    Node::ImageReference(_) => "ImageReference",
+    Node::MdxJsxTextElement(_) => "MdxJsxTextElement",
+    Node::Link(_) => "Link",
+    Node::LinkReference(_) => "LinkReference",
+    Node::Strong(_) => "Strong",
+    Node::Text(_) => "Text",
+    Node::Code(_) => "Code",
+    Node::Math(_) => "Math",
+    Node::MdxFlowExpression(_) => "MdxFlowExpression",
+    Node::Heading(_) => "Heading",
+    Node::Table(_) => "Table",
+    Node::ThematicBreak(_) => "ThematicBreak",
+    Node::TableRow(_) => "TableRow",
+    Node::TableCell(_) => "TableCell",
+    Node::ListItem(_) => "ListItem",
+    Node::Definition(_) => "Definition",
+    Node::Paragraph(_) => "Paragraph",
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn tree() -> Node {
+    markdown::to_mdast("# a *b*\n\nc\n\n> d\n", &markdown::ParseOptions::default()).unwrap()
+  }
+
+  struct Trace(Vec<String>);
+
+  impl<'n> Visitor<'n> for Trace {
+    fn enter(&mut self, node: &'n Node, context: &Context<'_, 'n>) -> Control {
+      self.0.push(format!("enter {} {:?}", type_name(node), context.path));
+      match node {
+        Node::BlockQuote(_) => Control::Skip,
+        _ => Control::Continue,
+      }
+    }
+
+    fn exit(&mut self, node: &'n Node, _context: &Context<'_, 'n>) -> Control {
+      self.0.push(format!("exit {}", type_name(node)));
+      Control::Continue
+    }
+  }
+
+  #[test]
+  fn enters_and_exits_in_order() {
+    let tree = tree();
+    let mut trace = Trace(vec![]);
+    visit(&tree, &mut trace);
+    assert_eq!(
+      trace.0,
+      vec![
+        "enter Root []",
+        "enter Heading [0]",
+        "enter Text [0, 0]",
+        "exit Text",
+        "enter Emphasis [0, 1]",
+        "enter Text [0, 1, 0]",
+        "exit Text",
+        "exit Emphasis",
+        "exit Heading",
+        "enter Paragraph [1]",
+        "enter Text [1, 0]",
+        "exit Text",
+        "exit Paragraph",
+        "enter BlockQuote [2]",
+        "exit BlockQuote",
+        "exit Root",
+      ]
+    );
+  }
+
+  #[test]
+  fn stops() {
+    let tree = tree();
+    let mut seen = vec![];
+    walk(&tree, |node, context| {
+      seen.push(type_name(node));
+      if context.parent().map(type_name) == Some("Emphasis") {
+        Control::Stop
+      } else {
+        Control::Continue
+      }
+    });
+    assert_eq!(seen, vec!["Root", "Heading", "Text", "Emphasis", "Text"]);
+  }
+
+  #[test]
+  fn changes_nodes() {
+    let mut tree = tree();
+    walk_mut(&mut tree, |node, context| {
+      if let Node::Text(text) = node {
+        if context.ancestors.contains(&"Heading") {
+          text.value = text.value.to_uppercase();
+        }
+      }
+      Control::Continue
+    });
+    let mut values = vec![];
+    walk(&tree, |node, _| {
+      if let Node::Text(text) = node {
+        values.push(text.value.clone());
+      }
+      Control::Continue
+    });
+    assert_eq!(values, vec!["A ", "B", "c", "d"]);
+  }
+}
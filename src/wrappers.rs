@@ -1,3 +1,4 @@
+use crate::section::{is_section_element, Section};
 use markdown::mdast::*;
 
 use serde::{
@@ -13,7 +14,7 @@ use serde::{
 // and makes them serializable via `serde`
 
 #[derive(Debug)]
-struct MyPoint(markdown::unist::Point);
+pub struct MyPoint(pub markdown::unist::Point);
 impl From<markdown::unist::Point> for MyPoint {
   fn from(p: markdown::unist::Point) -> MyPoint {
     MyPoint(p)
@@ -33,7 +34,7 @@ impl Serialize for MyPoint {
 }
 
 #[derive(Debug)]
-struct MyPosition(markdown::unist::Position);
+pub struct MyPosition(pub markdown::unist::Position);
 impl From<markdown::unist::Position> for MyPosition {
   fn from(p: markdown::unist::Position) -> MyPosition {
     MyPosition(p)
@@ -56,7 +57,7 @@ impl Serialize for MyPosition {
 }
 
 #[derive(Debug, Serialize)]
-enum MyReferenceKind {
+pub enum MyReferenceKind {
   Collapsed,
   Full,
   Shortcut,
@@ -72,7 +73,7 @@ impl From<markdown::mdast::ReferenceKind> for MyReferenceKind {
 }
 
 #[derive(Debug)]
-enum MyAttributeContent {
+pub enum MyAttributeContent {
   // AttributeContent(markdown::mdast::AttributeContent),
   Expression(String, Vec<markdown::mdast::Stop>),
   Property(markdown::mdast::MdxJsxAttribute),
@@ -116,7 +117,7 @@ impl Serialize for MyAttributeContent {
 }
 
 #[derive(Debug, Serialize)]
-enum MyAlignKind {
+pub enum MyAlignKind {
   Left,
   Center,
   Right,
@@ -134,7 +135,7 @@ impl From<markdown::mdast::AlignKind> for MyAlignKind {
 }
 
 #[derive(Debug)]
-enum MyAttributeValue {
+pub enum MyAttributeValue {
   Expression(String, Vec<markdown::mdast::Stop>),
   Literal(String),
 }
@@ -165,7 +166,7 @@ impl Serialize for MyAttributeValue {
 }
 
 #[derive(Debug)]
-struct MyMdxJsxAttribute(markdown::mdast::MdxJsxAttribute);
+pub struct MyMdxJsxAttribute(pub markdown::mdast::MdxJsxAttribute);
 impl From<markdown::mdast::MdxJsxAttribute> for MyMdxJsxAttribute {
   fn from(a: markdown::mdast::MdxJsxAttribute) -> MyMdxJsxAttribute {
     MyMdxJsxAttribute(a)
@@ -225,6 +226,8 @@ pub enum MyNode {
   ListItem(ListItem),
   Definition(Definition),
   Paragraph(Paragraph),
+  /// A heading and its content, see `crate::section`.
+  Section(Section),
 }
 
 /// Create a `MyNode` Enum from `markdown::mdast::Node` Enum.
@@ -234,6 +237,7 @@ impl From<Node> for MyNode {
       Node::Root(r) => MyNode::Root(r),
       Node::BlockQuote(r) => MyNode::BlockQuote(r),
       Node::FootnoteDefinition(r) => MyNode::FootnoteDefinition(r),
+      Node::MdxJsxFlowElement(r) if is_section_element(&r) => MyNode::Section(r.into()),
       Node::MdxJsxFlowElement(r) => MyNode::MdxJsxFlowElement(r),
       Node::List(r) => MyNode::List(r),
       Node::MdxjsEsm(r) => MyNode::MdxjsEsm(r),
@@ -704,7 +708,7 @@ impl Serialize for MyNode {
           &node
             .align
             .iter()
-            .map(|ak| MyAlignKind::from(ak.clone()))
+            .map(|ak| MyAlignKind::from(*ak))
             .collect::<Vec<MyAlignKind>>(),
         )?;
         state.end()
@@ -759,7 +763,7 @@ impl Serialize for MyNode {
           let pos: MyPosition = MyPosition::from(p);
           state.serialize_field("position", &pos)?;
         }
-        state.serialize_field("checked", &node.checked.clone().unwrap_or(false))?;
+        state.serialize_field("checked", &node.checked.unwrap_or(false))?;
         state.serialize_field(
           "children",
           &node
@@ -799,6 +803,7 @@ impl Serialize for MyNode {
         state.serialize_field("children", &new_children)?;
         state.end()
       }
+      MyNode::Section(ref section) => section.serialize(serializer),
       // default
       _ => {
         let mut state = serializer.serialize_struct("Default", 1)?;